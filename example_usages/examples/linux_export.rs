@@ -4,7 +4,9 @@ use std::{
     time::Duration,
 };
 
-use bevy_dmabuf::dmatex::{Dmatex, DmatexPlane, Resolution};
+use bevy_dmabuf::dmatex::{
+    ColorMatrix, ColorRange, Dmatex, DmatexPlane, DmatexTransform, Resolution,
+};
 use example_usages::TestInterfaceProxy;
 use tokio::{sync::Notify, time::timeout};
 use wlx_capture::{
@@ -59,7 +61,24 @@ async fn main() {
                     y: dmabuf.format.height,
                 },
                 format: dmabuf.format.fourcc.value,
-                flip_y: matches!(dmabuf.format.transform, Transform::Flipped),
+                // the transform is the output's wl_output.transform, wlx-capture doesn't report
+                // the y-invert flag of the frame
+                flip_y: false,
+                transform: match dmabuf.format.transform {
+                    Transform::Undefined | Transform::Normal => DmatexTransform::Normal,
+                    Transform::Rotated90 => DmatexTransform::Rotate90,
+                    Transform::Rotated180 => DmatexTransform::Rotate180,
+                    Transform::Rotated270 => DmatexTransform::Rotate270,
+                    Transform::Flipped => DmatexTransform::Flipped,
+                    Transform::Flipped90 => DmatexTransform::Flipped90,
+                    Transform::Flipped180 => DmatexTransform::Flipped180,
+                    Transform::Flipped270 => DmatexTransform::Flipped270,
+                },
+                srgb: false,
+                color_matrix: ColorMatrix::default(),
+                color_range: ColorRange::default(),
+                explicit_sync: None,
+                device: None,
             });
         }
         notify.notify_one();
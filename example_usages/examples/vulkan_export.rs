@@ -26,6 +26,8 @@ async fn main() {
     let planes = get_planes(&image)
         .map(|r| unsafe { vk.dev.get_image_subresource_layout(image.image, r) })
         .map(|p| DmatexPlane {
+            dmabuf_fd: image.fd.try_clone().unwrap().into(),
+            modifier: image.modifier,
             offset: p.offset as u32,
             stride: p.row_pitch as i32,
         })
@@ -33,15 +35,19 @@ async fn main() {
     println!("fd: {:?}", image.fd);
     proxy
         .dmatex(Dmatex {
-            dmabuf_fd: image.fd.try_clone().unwrap().into(),
             planes,
             res: Resolution {
                 x: res.width,
                 y: res.height,
             },
-            modifier: image.modifier,
-            format: vk_format_to_drm_fourcc(format).unwrap() as u32,
+            format: vk_format_to_drm_fourcc(format).unwrap(),
             flip_y: false,
+            transform: Default::default(),
+            srgb: false,
+            color_matrix: Default::default(),
+            color_range: Default::default(),
+            explicit_sync: None,
+            device: None,
         })
         .await
         .unwrap();
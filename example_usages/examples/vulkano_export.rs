@@ -131,9 +131,14 @@ async fn main() {
             x: size.x,
             y: size.y,
         },
-        format: vk_format_to_drm_fourcc(vk_format.into()).unwrap() as u32,
+        format: vk_format_to_drm_fourcc(vk_format.into()).unwrap(),
         flip_y: false,
+        transform: Default::default(),
         srgb: true,
+        color_matrix: Default::default(),
+        color_range: Default::default(),
+        explicit_sync: None,
+        device: None,
     };
 
     let data_len = size.x * size.y * 4;
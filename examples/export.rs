@@ -0,0 +1,119 @@
+use bevy::{
+    DefaultPlugins,
+    app::{App, AppExit, Startup, Update},
+    asset::{Assets, Handle, RenderAssetUsages},
+    color::Color,
+    core_pipeline::core_3d::Camera3d,
+    ecs::{
+        resource::Resource,
+        system::{Commands, Res, ResMut},
+    },
+    image::Image,
+    log::{error, info},
+    math::{Vec3, primitives::Cuboid},
+    pbr::{MeshMaterial3d, PointLight, StandardMaterial},
+    render::{
+        camera::{Camera, RenderTarget},
+        mesh::{Mesh, Mesh3d},
+        pipelined_rendering::PipelinedRenderingPlugin,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+    transform::components::Transform,
+    utils::default,
+};
+use bevy_dmabuf::{
+    dmatex::Dmatex,
+    export::{DmabufExportPlugin, ExportedDmatexs},
+    wgpu_init::add_dmabuf_init_plugin,
+};
+use tokio::sync::mpsc;
+
+#[zbus::proxy(
+    interface = "dev.schmarni.bevy_dmabuf.dmatex",
+    default_service = "dev.schmarni.bevy_dmabuf.dmatex",
+    default_path = "/dev/schmarni/bevy_dmabuf/dmatex"
+)]
+trait TestInterface {
+    fn dmatex(&self, dmabuf: Dmatex) -> zbus::Result<()>;
+}
+
+#[tokio::main]
+async fn main() -> AppExit {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Dmatex>();
+    tokio::spawn(async move {
+        let conn = zbus::Connection::session().await.unwrap();
+        let proxy = TestInterfaceProxy::new(&conn).await.unwrap();
+        while let Some(dmatex) = rx.recv().await {
+            if let Err(err) = proxy.dmatex(dmatex).await {
+                error!("unable to send dmatex: {err}");
+            }
+        }
+    });
+    App::new()
+        .insert_resource(Sender(tx))
+        .add_plugins(add_dmabuf_init_plugin(DefaultPlugins).disable::<PipelinedRenderingPlugin>())
+        .add_plugins(DmabufExportPlugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, send_dmatex)
+        .run()
+}
+
+fn send_dmatex(exported: Res<ExportedDmatexs>, target: Res<Target>, sender: Res<Sender>) {
+    if let Some(dmatex) = exported.take_dmatex(&target.0) {
+        info!("sending exported dmatex");
+        _ = sender.0.send(dmatex);
+    }
+}
+
+// render a simple 3D scene into an exported image
+fn setup(
+    mut cmds: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    exported: Res<ExportedDmatexs>,
+) {
+    let mut image = Image::new_uninit(
+        Extent3d {
+            width: 512,
+            height: 512,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST
+        | TextureUsages::RENDER_ATTACHMENT;
+    let target = images.add(image);
+    exported.export(&target);
+
+    cmds.spawn((
+        Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.8, 0.2, 0.8))),
+        Transform::from_xyz(0.0, 0.5, 0.0),
+    ));
+    cmds.spawn((
+        PointLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_xyz(4.0, 8.0, 4.0),
+    ));
+    cmds.spawn((
+        Camera3d::default(),
+        Camera {
+            target: RenderTarget::Image(target.clone().into()),
+            ..default()
+        },
+        Transform::from_xyz(-2.5, 4.5, 9.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+    cmds.insert_resource(Target(target));
+}
+
+#[derive(Resource)]
+struct Target(Handle<Image>);
+#[derive(Resource)]
+struct Sender(mpsc::UnboundedSender<Dmatex>);
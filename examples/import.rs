@@ -1,7 +1,4 @@
-use std::{
-    os::fd::AsFd,
-    sync::{Mutex, OnceLock},
-};
+use std::{os::fd::AsFd, sync::Mutex};
 
 use bevy::{
     DefaultPlugins,
@@ -12,7 +9,7 @@ use bevy::{
     ecs::{
         resource::Resource,
         schedule::{IntoScheduleConfigs, common_conditions::not},
-        system::{Commands, Res, ResMut},
+        system::{Commands, Res, ResMut, Single},
     },
     image::Image,
     input::{common_conditions::input_pressed, keyboard::KeyCode},
@@ -23,20 +20,17 @@ use bevy::{
     },
     pbr::{MeshMaterial3d, PointLight, StandardMaterial},
     render::{
-        Render, RenderApp,
+        alpha::AlphaMode,
         mesh::{Mesh, Mesh3d},
         pipelined_rendering::PipelinedRenderingPlugin,
-        renderer::RenderDevice,
     },
     transform::components::Transform,
     utils::default,
 };
 use bevy_dmabuf::{
     dmatex::{Dmatex, DmatexPlane},
-    import::{
-        DmabufImportPlugin, DmatexUsage, DropCallback, ImportedDmatexs, ImportedTexture,
-        import_texture,
-    },
+    import::{DmabufImportPlugin, DmatexUsage, ImportedDmatexs},
+    stream::{DmabufStream, DmabufStreamMode},
     wgpu_init::add_dmabuf_init_plugin,
 };
 use tokio::sync::mpsc;
@@ -59,16 +53,12 @@ async fn main() -> AppExit {
     let mut app = App::new();
     app.insert_resource(Receiver(rx.into()))
         .add_plugins(add_dmabuf_init_plugin(DefaultPlugins).disable::<PipelinedRenderingPlugin>())
-        .add_plugins(DmabufImportPlugin)
+        .add_plugins(DmabufImportPlugin::default())
         .add_systems(Startup, setup)
         .add_systems(
             PostUpdate,
             import_tex.run_if(not(input_pressed(KeyCode::Space))),
         );
-    app.sub_app_mut(RenderApp)
-        .add_systems(Render, |render_dev: Res<RenderDevice>| {
-            _ = RENDER_DEVICE.set(render_dev.clone());
-        });
     app.run()
 }
 
@@ -78,12 +68,23 @@ fn import_tex(
     mut images: ResMut<Assets<Image>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     handle: Res<CubeMat>,
+    stream: Single<&DmabufStream>,
 ) {
     while let Ok(buf) = receiv.0.try_recv() {
-        info!("inserting imported dmatex");
-        let image = dmatexs.insert_imported_dmatex(&mut images, buf);
-        let mat = materials.get_mut(&handle.0).unwrap();
-        mat.base_color_texture = Some(image);
+        info!("submitting dmatex");
+        if let Err(err) = dmatexs.submit(&mut images, &stream, buf, DmatexUsage::SAMPLING, None) {
+            error!("invalid dmatex: {err}");
+            continue;
+        }
+        let alpha_mode = if dmatexs.has_alpha(stream.handle()).unwrap_or(true) {
+            AlphaMode::Blend
+        } else {
+            AlphaMode::Opaque
+        };
+        // the material keeps showing the stream, only the alpha mode can change
+        if materials.get(&handle.0).unwrap().alpha_mode != alpha_mode {
+            materials.get_mut(&handle.0).unwrap().alpha_mode = alpha_mode;
+        }
     }
 }
 
@@ -92,9 +93,13 @@ fn setup(
     mut cmds: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    dmatexs: Res<ImportedDmatexs>,
 ) {
+    let stream = dmatexs.create_stream(&mut images, DmabufStreamMode::Mailbox);
     let mat_handle = materials.add(StandardMaterial {
         base_color: Color::WHITE,
+        base_color_texture: Some(stream.handle().clone()),
         ..default()
     });
     // cube
@@ -102,6 +107,7 @@ fn setup(
         Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
         MeshMaterial3d(mat_handle.clone()),
         Transform::from_xyz(0.0, 0.5, 0.0),
+        stream,
     ));
     cmds.insert_resource(CubeMat(mat_handle));
     // circular base
@@ -125,27 +131,18 @@ fn setup(
 }
 
 #[derive(Resource)]
-struct Receiver(mpsc::UnboundedReceiver<ImportedTexture>);
+struct Receiver(mpsc::UnboundedReceiver<Dmatex>);
 #[derive(Resource)]
 struct CubeMat(Handle<StandardMaterial>);
 
-static RENDER_DEVICE: OnceLock<RenderDevice> = OnceLock::new();
-
 pub struct TestInterface {
-    pub dmatex_channel: mpsc::UnboundedSender<ImportedTexture>,
+    pub dmatex_channel: mpsc::UnboundedSender<Dmatex>,
 }
 
 #[zbus::interface(name = "dev.schmarni.bevy_dmabuf.dmatex")]
 impl TestInterface {
     fn dmatex(&self, dmabuf: Dmatex) {
         info!("got dmatex");
-        let tex = import_texture(
-            RENDER_DEVICE.wait(),
-            dmabuf,
-            DropCallback(None),
-            DmatexUsage::Sampling,
-        )
-        .unwrap();
-        _ = self.dmatex_channel.send(tex);
+        _ = self.dmatex_channel.send(dmabuf);
     }
 }
@@ -0,0 +1,31 @@
+// Copies an imported dmatex into a texture owned by bevy, applying its transform, forcing alpha to
+// one for formats without alpha and swapping red and blue for formats vulkan only has as rgba.
+
+struct Params {
+    // source texel = transform * vec3(output texel, 1)
+    transform_x: vec4<i32>,
+    transform_y: vec4<i32>,
+    // 1 if the source has no alpha channel, its undefined X channel is replaced by 1.0
+    opaque: u32,
+    // 1 if red and blue of the source are swapped
+    swizzled: u32,
+}
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: Params;
+
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // fullscreen triangle
+    let uv = vec2(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fragment(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pos = vec3(vec2<i32>(position.xy), 1);
+    let source_pos = vec2(dot(params.transform_x.xyz, pos), dot(params.transform_y.xyz, pos));
+    let texel = textureLoad(source, source_pos, 0);
+    let color = select(texel, texel.bgra, params.swizzled != 0u);
+    return select(color, vec4(color.rgb, 1.0), params.opaque != 0u);
+}
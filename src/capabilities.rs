@@ -0,0 +1,247 @@
+use ash::{ext, vk};
+use bevy::{ecs::resource::Resource, render::renderer::RenderDevice};
+use wgpu::hal::vulkan::Api as Vulkan;
+
+use crate::{
+    dmatex::Resolution,
+    format_mapping::{
+        PackedFormat, WgpuFormat, YuvFormat, drm_fourcc_to_vk_format, fourcc_to_wgpu,
+        fourcc_to_yuv, get_drm_modifiers, wgpu_fourccs,
+    },
+    import::{
+        DmatexUsage, ImportError, TRANSFER_USAGES, get_image_format_properties, vk_image_usage,
+    },
+    modifier::Modifier,
+};
+
+/// The fourcc and modifier combinations the render device can import, meant to be sent to
+/// producers before they allocate their buffers.
+#[derive(
+    Resource, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, zvariant::Type,
+)]
+pub struct DmabufCapabilities {
+    /// `dev_t` of the render (or primary) node of the render device, requires
+    /// `VK_EXT_physical_device_drm`
+    pub main_device: Option<u64>,
+    pub formats: Vec<FormatCapabilities>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, zvariant::Type)]
+pub struct FormatCapabilities {
+    pub fourcc: u32,
+    pub modifiers: Vec<ModifierCapabilities>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, zvariant::Type)]
+pub struct ModifierCapabilities {
+    pub modifier: u64,
+    /// number of planes a dmatex with this modifier has to have
+    pub plane_count: u32,
+    pub max_extent: Resolution,
+}
+
+impl DmabufCapabilities {
+    /// Queries every importable fourcc and modifier of `device`, formats without importable
+    /// modifiers or whose wgpu format needs features the device lacks are left out.
+    pub fn query(device: &RenderDevice) -> Result<Self, ImportError> {
+        let features = device.features();
+        let (main_device, formats) = unsafe {
+            device
+                .wgpu_device()
+                .as_hal::<Vulkan, _, _>(|dev| -> Result<_, ImportError> {
+                    let dev = dev.ok_or(ImportError::NotVulkan)?;
+                    let instance = dev.shared_instance().raw_instance();
+                    let phys_dev = dev.raw_physical_device();
+                    let main_device = drm_device(dev).and_then(|drm| drm.render.or(drm.primary));
+                    let formats = wgpu_fourccs()
+                        .filter_map(|fourcc| {
+                            if let Some(yuv) = fourcc_to_yuv(fourcc) {
+                                let modifiers = query_yuv_modifiers(instance, phys_dev, &yuv);
+                                return (!modifiers.is_empty())
+                                    .then_some(FormatCapabilities { fourcc, modifiers });
+                            }
+                            let modifiers = match fourcc_to_wgpu(fourcc)? {
+                                WgpuFormat::Native(format) | WgpuFormat::Swizzled(format)
+                                    if !features.contains(format.required_features()) =>
+                                {
+                                    return None;
+                                }
+                                WgpuFormat::Native(_) | WgpuFormat::Swizzled(_) => query_modifiers(
+                                    instance,
+                                    phys_dev,
+                                    drm_fourcc_to_vk_format(fourcc)?,
+                                ),
+                                WgpuFormat::Packed(packed) => {
+                                    query_packed_modifiers(instance, phys_dev, &packed)
+                                }
+                            };
+                            (!modifiers.is_empty())
+                                .then_some(FormatCapabilities { fourcc, modifiers })
+                        })
+                        .collect();
+                    Ok((main_device, formats))
+                })
+        }?;
+        Ok(Self {
+            main_device,
+            formats,
+        })
+    }
+
+    /// The modifiers `fourcc` can be imported with.
+    pub fn modifiers(&self, fourcc: u32) -> &[ModifierCapabilities] {
+        self.formats
+            .iter()
+            .find(|format| format.fourcc == fourcc)
+            .map(|format| format.modifiers.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// The DRM nodes of a Vulkan device, as `dev_t`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, zvariant::Type,
+)]
+pub struct DrmDevice {
+    pub primary: Option<u64>,
+    pub render: Option<u64>,
+}
+
+impl DrmDevice {
+    /// The DRM nodes of the render device, returns [`None`] if they are unknown because
+    /// `VK_EXT_physical_device_drm` isn't supported.
+    pub fn query(device: &RenderDevice) -> Option<Self> {
+        unsafe {
+            device
+                .wgpu_device()
+                .as_hal::<Vulkan, _, _>(|dev| dev.and_then(|dev| drm_device(dev)))
+        }
+    }
+
+    /// Whether `dev` is one of the nodes of this device.
+    pub fn contains(&self, dev: u64) -> bool {
+        self.primary == Some(dev) || self.render == Some(dev)
+    }
+}
+
+/// Queries the DRM nodes of `dev`, returns [`None`] if `VK_EXT_physical_device_drm` isn't enabled.
+pub(crate) unsafe fn drm_device(dev: &wgpu::hal::vulkan::Device) -> Option<DrmDevice> {
+    if !dev
+        .enabled_device_extensions()
+        .contains(&ext::physical_device_drm::NAME)
+    {
+        return None;
+    }
+    let mut drm_props = vk::PhysicalDeviceDrmPropertiesEXT::default();
+    let mut props = vk::PhysicalDeviceProperties2::default().push_next(&mut drm_props);
+    unsafe {
+        dev.shared_instance()
+            .raw_instance()
+            .get_physical_device_properties2(dev.raw_physical_device(), &mut props)
+    };
+    let dev_t = |major: i64, minor: i64| libc::makedev(major as u32, minor as u32);
+    Some(DrmDevice {
+        primary: (drm_props.has_primary == vk::TRUE)
+            .then(|| dev_t(drm_props.primary_major, drm_props.primary_minor)),
+        render: (drm_props.has_render == vk::TRUE)
+            .then(|| dev_t(drm_props.render_major, drm_props.render_minor)),
+    })
+}
+
+/// Packed formats are imported as raw integer texels, 24-bit pixels are split into three texels
+/// which only works for linear buffers.
+unsafe fn query_packed_modifiers(
+    instance: &ash::Instance,
+    phys_dev: vk::PhysicalDevice,
+    packed: &PackedFormat,
+) -> Vec<ModifierCapabilities> {
+    let texels_per_pixel = packed.texels_per_pixel();
+    let mut modifiers = unsafe { query_modifiers(instance, phys_dev, packed.raw_format().1) };
+    if texels_per_pixel > 1 {
+        modifiers.retain(|modifier| Modifier(modifier.modifier).is_linear());
+    }
+    for modifier in &mut modifiers {
+        modifier.max_extent.x /= texels_per_pixel;
+    }
+    modifiers
+}
+
+/// Yuv formats are imported plane by plane with the modifier of the dmatex, so a modifier has to
+/// be importable for the format of every plane, as a single plane without compression metadata.
+unsafe fn query_yuv_modifiers(
+    instance: &ash::Instance,
+    phys_dev: vk::PhysicalDevice,
+    yuv: &YuvFormat,
+) -> Vec<ModifierCapabilities> {
+    let mut planes = yuv.planes.iter().map(|plane| {
+        let mut modifiers = unsafe { query_modifiers(instance, phys_dev, plane.vk_format) };
+        modifiers.retain(|modifier| {
+            modifier.plane_count == 1 && !Modifier(modifier.modifier).has_aux_plane()
+        });
+        // subsampled planes are smaller than the image
+        for modifier in &mut modifiers {
+            let max_extent = &mut modifier.max_extent;
+            max_extent.x = max_extent.x.saturating_mul(plane.subsampling.0);
+            max_extent.y = max_extent.y.saturating_mul(plane.subsampling.1);
+        }
+        modifiers
+    });
+    let Some(mut modifiers) = planes.next() else {
+        return Vec::new();
+    };
+    for plane in planes {
+        modifiers.retain_mut(|modifier| {
+            let Some(other) = plane
+                .iter()
+                .find(|other| other.modifier == modifier.modifier)
+            else {
+                return false;
+            };
+            modifier.max_extent.x = modifier.max_extent.x.min(other.max_extent.x);
+            modifier.max_extent.y = modifier.max_extent.y.min(other.max_extent.y);
+            true
+        });
+    }
+    for modifier in &mut modifiers {
+        modifier.plane_count = yuv.planes.len() as u32;
+    }
+    modifiers
+}
+
+unsafe fn query_modifiers(
+    instance: &ash::Instance,
+    phys_dev: vk::PhysicalDevice,
+    format: vk::Format,
+) -> Vec<ModifierCapabilities> {
+    // every dmatex gets sampled, directly or by its conversion, and copied by the ownership
+    // transfers
+    let usage = vk_image_usage(DmatexUsage::SAMPLING.texture_usages() | TRANSFER_USAGES);
+    get_drm_modifiers(instance, phys_dev, format)
+        .1
+        .into_iter()
+        .filter_map(|props| {
+            let (image_props, external_props) = unsafe {
+                get_image_format_properties(
+                    instance,
+                    phys_dev,
+                    format,
+                    &[],
+                    props.drm_format_modifier,
+                    usage,
+                )
+            }
+            .ok()?;
+            external_props
+                .external_memory_features
+                .contains(vk::ExternalMemoryFeatureFlags::IMPORTABLE)
+                .then_some(ModifierCapabilities {
+                    modifier: props.drm_format_modifier,
+                    plane_count: props.drm_format_modifier_plane_count,
+                    max_extent: Resolution {
+                        x: image_props.max_extent.width,
+                        y: image_props.max_extent.height,
+                    },
+                })
+        })
+        .collect()
+}
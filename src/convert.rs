@@ -0,0 +1,649 @@
+use std::sync::Mutex;
+
+use bevy::{
+    ecs::{resource::Resource, world::FromWorld, world::World},
+    platform::collections::HashMap,
+    render::{
+        render_resource::{
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, Buffer, ComputePipeline,
+            RawFragmentState, RawRenderPipelineDescriptor, RawVertexState, RenderPipeline,
+            ShaderModule, ShaderStages, StorageTextureAccess, Texture, TextureSampleType,
+            TextureView,
+            binding_types::{texture_2d, texture_storage_2d, uniform_buffer_sized},
+        },
+        renderer::RenderDevice,
+    },
+    utils::default,
+};
+use wgpu::util::BufferInitDescriptor;
+
+use crate::{
+    dmatex::{ColorMatrix, ColorRange, Dmatex, DmatexTransform},
+    format_mapping::{PackedFormat, YuvFormat, YuvLayout},
+};
+
+const SHADER: &str = include_str!("convert.wgsl");
+const BLIT_SHADER: &str = include_str!("blit.wgsl");
+const UNPACK_SHADER: &str = include_str!("unpack.wgsl");
+const WORKGROUP_SIZE: u32 = 8;
+
+/// Output formats the conversion pipeline is compiled for.
+const OUTPUT_FORMATS: [(wgpu::TextureFormat, &str); 2] = [
+    (wgpu::TextureFormat::Rgba8Unorm, "rgba8unorm"),
+    (wgpu::TextureFormat::Rgba16Float, "rgba16float"),
+];
+
+/// Source planes of an imported dmatex that have to be converted into `output` every frame.
+#[derive(Clone, Debug)]
+pub(crate) struct ConvertSource {
+    kind: ConvertKind,
+    planes: Vec<TextureView>,
+    params: Buffer,
+    output: TextureView,
+    output_format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ConvertKind {
+    /// yuv planes converted to rgb by a compute pass
+    Yuv,
+    /// a single texture of the output format copied by a render pass
+    Blit,
+    /// raw integer texels of a packed format unpacked by a compute pass
+    Unpack,
+}
+
+impl ConvertSource {
+    pub(crate) fn new_yuv(
+        device: &RenderDevice,
+        planes: Vec<TextureView>,
+        yuv: &YuvFormat,
+        buf: &Dmatex,
+        output: &Texture,
+    ) -> Self {
+        let rows = ycbcr_to_rgb_rows(buf.color_matrix, buf.color_range, yuv.bit_depth);
+        let mut params = Vec::with_capacity(96);
+        for value in rows.as_flattened() {
+            params.extend_from_slice(&value.to_ne_bytes());
+        }
+        params.extend_from_slice(&layout_index(yuv.layout).to_ne_bytes());
+        params.resize(64, 0);
+        params.extend_from_slice(&transform_bytes(buf, output.size()));
+        Self::new(device, ConvertKind::Yuv, planes, &params, output)
+    }
+
+    /// Copies `source` into `output` every frame, making it upright on the way. `opaque` replaces
+    /// the alpha channel with one, `swizzled` swaps red and blue.
+    pub(crate) fn new_blit(
+        device: &RenderDevice,
+        source: TextureView,
+        buf: &Dmatex,
+        opaque: bool,
+        swizzled: bool,
+        output: &Texture,
+    ) -> Self {
+        let mut params = transform_bytes(buf, output.size());
+        params.extend_from_slice(&u32::from(opaque).to_ne_bytes());
+        params.extend_from_slice(&u32::from(swizzled).to_ne_bytes());
+        params.resize(48, 0);
+        Self::new(device, ConvertKind::Blit, vec![source], &params, output)
+    }
+
+    /// Unpacks the raw texels of `source` into `output` every frame, making it upright on the way.
+    pub(crate) fn new_unpack(
+        device: &RenderDevice,
+        source: TextureView,
+        packed: &PackedFormat,
+        buf: &Dmatex,
+        output: &Texture,
+    ) -> Self {
+        let mut params = Vec::with_capacity(80);
+        for (shift, _) in packed.channels {
+            params.extend_from_slice(&shift.to_ne_bytes());
+        }
+        for (_, bits) in packed.channels {
+            params.extend_from_slice(&bits.to_ne_bytes());
+        }
+        params.extend_from_slice(&packed.bytes_per_pixel.to_ne_bytes());
+        params.resize(48, 0);
+        params.extend_from_slice(&transform_bytes(buf, output.size()));
+        Self::new(device, ConvertKind::Unpack, vec![source], &params, output)
+    }
+
+    fn new(
+        device: &RenderDevice,
+        kind: ConvertKind,
+        planes: Vec<TextureView>,
+        params: &[u8],
+        output: &Texture,
+    ) -> Self {
+        let params = device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("dmatex convert params"),
+            contents: params,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        Self {
+            kind,
+            planes,
+            params,
+            output: output.create_view(&wgpu::TextureViewDescriptor::default()),
+            output_format: output.format(),
+            size: output.size(),
+        }
+    }
+}
+
+/// The affine transform from output texel coordinates to source texel coordinates, as two
+/// `vec4<i32>` rows.
+fn transform_bytes(buf: &Dmatex, size: wgpu::Extent3d) -> Vec<u8> {
+    let rows = source_transform(
+        buf.transform,
+        buf.flip_y,
+        size.width as i32,
+        size.height as i32,
+    );
+    rows.iter()
+        .flat_map(|row| [row[0], row[1], row[2], 0])
+        .flat_map(|value| value.to_ne_bytes())
+        .collect()
+}
+
+/// Maps a texel of the upright `width` x `height` output to the texel of the buffer it was
+/// moved to by `transform`. `flip_y` flips the rows of the buffer, it is undone first.
+fn source_transform(
+    transform: DmatexTransform,
+    flip_y: bool,
+    width: i32,
+    height: i32,
+) -> [[i32; 3]; 2] {
+    let (w, h) = (width - 1, height - 1);
+    // rows of source = rows * (x, y, 1)
+    let rows = match transform {
+        DmatexTransform::Normal => [[1, 0, 0], [0, 1, 0]],
+        DmatexTransform::Rotate90 => [[0, 1, 0], [-1, 0, w]],
+        DmatexTransform::Rotate180 => [[-1, 0, w], [0, -1, h]],
+        DmatexTransform::Rotate270 => [[0, -1, h], [1, 0, 0]],
+        DmatexTransform::Flipped => [[-1, 0, w], [0, 1, 0]],
+        DmatexTransform::Flipped90 => [[0, 1, 0], [1, 0, 0]],
+        DmatexTransform::Flipped180 => [[1, 0, 0], [0, -1, h]],
+        DmatexTransform::Flipped270 => [[0, -1, h], [-1, 0, w]],
+    };
+    if !flip_y {
+        return rows;
+    }
+    let buffer_height = if transform.swaps_dimensions() {
+        width
+    } else {
+        height
+    };
+    let [x, y] = rows;
+    [x, [-y[0], -y[1], buffer_height - 1 - y[2]]]
+}
+
+fn layout_index(layout: YuvLayout) -> u32 {
+    match layout {
+        YuvLayout::Nv12 => 0,
+        YuvLayout::Nv21 => 1,
+        YuvLayout::Yuv420 => 2,
+        YuvLayout::Yuyv => 3,
+    }
+}
+
+/// Builds the rows of the affine transform from sampled (normalized) YCbCr values to RGB,
+/// folding the quantization range and the bit depth into the matrix.
+fn ycbcr_to_rgb_rows(matrix: ColorMatrix, range: ColorRange, bit_depth: u32) -> [[f32; 4]; 3] {
+    let (kr, kb) = match matrix {
+        ColorMatrix::Bt601 => (0.299, 0.114),
+        ColorMatrix::Bt709 => (0.2126, 0.0722),
+        ColorMatrix::Bt2020 => (0.2627, 0.0593),
+    };
+    let kg = 1.0 - kr - kb;
+
+    // the component is stored in the most significant bits of an 8 or 16 bit container
+    let container_depth = if bit_depth > 8 { 16 } else { 8 };
+    let container_max = ((1u32 << container_depth) - 1) as f32;
+    let code_scale = container_max / (1u32 << (container_depth - bit_depth)) as f32;
+    let step = (1u32 << (bit_depth - 8)) as f32;
+    let max_code = ((1u32 << bit_depth) - 1) as f32;
+    let chroma_center = (1u32 << (bit_depth - 1)) as f32;
+
+    // y = sampled * y_scale + y_offset, same for chroma
+    let (y_scale, y_offset, c_scale, c_offset) = match range {
+        ColorRange::Limited => (
+            code_scale / (219.0 * step),
+            -16.0 / 219.0,
+            code_scale / (224.0 * step),
+            -128.0 / 224.0,
+        ),
+        ColorRange::Full => (
+            code_scale / max_code,
+            0.0,
+            code_scale / max_code,
+            -chroma_center / max_code,
+        ),
+    };
+
+    let r_cr = 2.0 * (1.0 - kr);
+    let g_cb = -2.0 * kb * (1.0 - kb) / kg;
+    let g_cr = -2.0 * kr * (1.0 - kr) / kg;
+    let b_cb = 2.0 * (1.0 - kb);
+    [
+        [y_scale, 0.0, r_cr * c_scale, y_offset + r_cr * c_offset],
+        [
+            y_scale,
+            g_cb * c_scale,
+            g_cr * c_scale,
+            y_offset + (g_cb + g_cr) * c_offset,
+        ],
+        [y_scale, b_cb * c_scale, 0.0, y_offset + b_cb * c_offset],
+    ]
+}
+
+#[derive(Resource)]
+pub(crate) struct ConvertPipelines {
+    yuv: HashMap<wgpu::TextureFormat, (BindGroupLayout, ComputePipeline)>,
+    blit_layout: BindGroupLayout,
+    blit_shader: ShaderModule,
+    /// created on first use, since any importable format can be blitted
+    blit: Mutex<HashMap<wgpu::TextureFormat, RenderPipeline>>,
+    unpack: HashMap<wgpu::TextureFormat, (BindGroupLayout, ComputePipeline)>,
+}
+
+impl FromWorld for ConvertPipelines {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let pipelines = OUTPUT_FORMATS
+            .into_iter()
+            .map(|(format, wgsl_format)| {
+                let layout = device.create_bind_group_layout(
+                    "dmatex convert bind group layout",
+                    &BindGroupLayoutEntries::sequential(
+                        ShaderStages::COMPUTE,
+                        (
+                            texture_2d(TextureSampleType::Float { filterable: false }),
+                            texture_2d(TextureSampleType::Float { filterable: false }),
+                            texture_2d(TextureSampleType::Float { filterable: false }),
+                            texture_storage_2d(format, StorageTextureAccess::WriteOnly),
+                            uniform_buffer_sized(false, None),
+                        ),
+                    ),
+                );
+                let shader =
+                    device.create_and_validate_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("dmatex convert shader"),
+                        source: wgpu::ShaderSource::Wgsl(
+                            SHADER.replace("OUTPUT_FORMAT", wgsl_format).into(),
+                        ),
+                    });
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("dmatex convert pipeline layout"),
+                        bind_group_layouts: &[&layout],
+                        push_constant_ranges: &[],
+                    });
+                let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("dmatex convert pipeline"),
+                    layout: Some(&pipeline_layout),
+                    module: &shader,
+                    entry_point: Some("main"),
+                    compilation_options: Default::default(),
+                    cache: None,
+                });
+                (format, (layout, pipeline))
+            })
+            .collect();
+
+        let blit_layout = device.create_bind_group_layout(
+            "dmatex blit bind group layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                    uniform_buffer_sized(false, None),
+                ),
+            ),
+        );
+        let blit_shader = device.create_and_validate_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("dmatex blit shader"),
+            source: wgpu::ShaderSource::Wgsl(BLIT_SHADER.into()),
+        });
+
+        let unpack = OUTPUT_FORMATS
+            .into_iter()
+            .map(|(format, wgsl_format)| {
+                let layout = device.create_bind_group_layout(
+                    "dmatex unpack bind group layout",
+                    &BindGroupLayoutEntries::sequential(
+                        ShaderStages::COMPUTE,
+                        (
+                            texture_2d(TextureSampleType::Uint),
+                            texture_storage_2d(format, StorageTextureAccess::WriteOnly),
+                            uniform_buffer_sized(false, None),
+                        ),
+                    ),
+                );
+                let shader =
+                    device.create_and_validate_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("dmatex unpack shader"),
+                        source: wgpu::ShaderSource::Wgsl(
+                            UNPACK_SHADER.replace("OUTPUT_FORMAT", wgsl_format).into(),
+                        ),
+                    });
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("dmatex unpack pipeline layout"),
+                        bind_group_layouts: &[&layout],
+                        push_constant_ranges: &[],
+                    });
+                let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("dmatex unpack pipeline"),
+                    layout: Some(&pipeline_layout),
+                    module: &shader,
+                    entry_point: Some("main"),
+                    compilation_options: Default::default(),
+                    cache: None,
+                });
+                (format, (layout, pipeline))
+            })
+            .collect();
+        Self {
+            yuv: pipelines,
+            blit_layout,
+            blit_shader,
+            blit: default(),
+            unpack,
+        }
+    }
+}
+
+impl ConvertPipelines {
+    /// Records the conversion of `source` into its output texture.
+    pub(crate) fn record(
+        &self,
+        device: &RenderDevice,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &ConvertSource,
+    ) {
+        match source.kind {
+            ConvertKind::Yuv => self.record_yuv(device, encoder, source),
+            ConvertKind::Blit => self.record_blit(device, encoder, source),
+            ConvertKind::Unpack => self.record_unpack(device, encoder, source),
+        }
+    }
+
+    fn record_yuv(
+        &self,
+        device: &RenderDevice,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &ConvertSource,
+    ) {
+        let Some((layout, pipeline)) = self.yuv.get(&source.output_format) else {
+            return;
+        };
+        // unused planes still have to be bound, the shader ignores them
+        let plane = |i: usize| source.planes.get(i).unwrap_or(&source.planes[0]);
+        let bind_group = device.create_bind_group(
+            "dmatex convert bind group",
+            layout,
+            &BindGroupEntries::sequential((
+                plane(0),
+                plane(1),
+                plane(2),
+                &source.output,
+                source.params.as_entire_binding(),
+            )),
+        );
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("dmatex convert pass"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(
+            source.size.width.div_ceil(WORKGROUP_SIZE),
+            source.size.height.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+    }
+
+    fn record_unpack(
+        &self,
+        device: &RenderDevice,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &ConvertSource,
+    ) {
+        let Some((layout, pipeline)) = self.unpack.get(&source.output_format) else {
+            return;
+        };
+        let bind_group = device.create_bind_group(
+            "dmatex unpack bind group",
+            layout,
+            &BindGroupEntries::sequential((
+                &source.planes[0],
+                &source.output,
+                source.params.as_entire_binding(),
+            )),
+        );
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("dmatex unpack pass"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(
+            source.size.width.div_ceil(WORKGROUP_SIZE),
+            source.size.height.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+    }
+
+    fn record_blit(
+        &self,
+        device: &RenderDevice,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &ConvertSource,
+    ) {
+        let pipeline = self.blit_pipeline(device, source.output_format);
+        let bind_group = device.create_bind_group(
+            "dmatex blit bind group",
+            &self.blit_layout,
+            &BindGroupEntries::sequential((&source.planes[0], source.params.as_entire_binding())),
+        );
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("dmatex blit pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &source.output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    fn blit_pipeline(&self, device: &RenderDevice, format: wgpu::TextureFormat) -> RenderPipeline {
+        #[expect(clippy::unwrap_used)]
+        let mut pipelines = self.blit.lock().unwrap();
+        pipelines
+            .entry(format)
+            .or_insert_with(|| {
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("dmatex blit pipeline layout"),
+                        bind_group_layouts: &[&self.blit_layout],
+                        push_constant_ranges: &[],
+                    });
+                device.create_render_pipeline(&RawRenderPipelineDescriptor {
+                    label: Some("dmatex blit pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: RawVertexState {
+                        module: &self.blit_shader,
+                        entry_point: Some("vertex"),
+                        compilation_options: default(),
+                        buffers: &[],
+                    },
+                    fragment: Some(RawFragmentState {
+                        module: &self.blit_shader,
+                        entry_point: Some("fragment"),
+                        compilation_options: default(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: default(),
+                    depth_stencil: None,
+                    multisample: default(),
+                    multiview: None,
+                    cache: None,
+                })
+            })
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSFORMS: [DmatexTransform; 8] = [
+        DmatexTransform::Normal,
+        DmatexTransform::Rotate90,
+        DmatexTransform::Rotate180,
+        DmatexTransform::Rotate270,
+        DmatexTransform::Flipped,
+        DmatexTransform::Flipped90,
+        DmatexTransform::Flipped180,
+        DmatexTransform::Flipped270,
+    ];
+
+    /// Moves the upright texel `(x, y)` of a `width` x `height` image to the buffer, step by step:
+    /// flip around the vertical axis, rotate counter-clockwise, then flip the rows.
+    fn to_buffer(
+        transform: DmatexTransform,
+        flip_y: bool,
+        (mut x, mut y): (i32, i32),
+        (mut width, mut height): (i32, i32),
+    ) -> (i32, i32) {
+        let (flipped, rotations) = match transform {
+            DmatexTransform::Normal => (false, 0),
+            DmatexTransform::Rotate90 => (false, 1),
+            DmatexTransform::Rotate180 => (false, 2),
+            DmatexTransform::Rotate270 => (false, 3),
+            DmatexTransform::Flipped => (true, 0),
+            DmatexTransform::Flipped90 => (true, 1),
+            DmatexTransform::Flipped180 => (true, 2),
+            DmatexTransform::Flipped270 => (true, 3),
+        };
+        if flipped {
+            x = width - 1 - x;
+        }
+        for _ in 0..rotations {
+            // the top right corner becomes the top left one
+            (x, y) = (y, width - 1 - x);
+            (width, height) = (height, width);
+        }
+        if flip_y {
+            y = height - 1 - y;
+        }
+        (x, y)
+    }
+
+    #[test]
+    fn source_transforms() {
+        let (width, height) = (3, 2);
+        for transform in TRANSFORMS {
+            for flip_y in [false, true] {
+                let [rx, ry] = source_transform(transform, flip_y, width, height);
+                for (x, y) in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
+                    let source = (rx[0] * x + rx[1] * y + rx[2], ry[0] * x + ry[1] * y + ry[2]);
+                    assert_eq!(
+                        source,
+                        to_buffer(transform, flip_y, (x, y), (width, height)),
+                        "{transform:?}, flip_y: {flip_y}, texel ({x}, {y})"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flip_y_before_rotation() {
+        // flipping the rows of the 2x3 buffer turns the rotation into a transposition, flipping
+        // the upright 3x2 image instead would give (1 - y, 2 - x)
+        assert_eq!(
+            source_transform(DmatexTransform::Rotate90, true, 3, 2),
+            [[0, 1, 0], [1, 0, 0]]
+        );
+    }
+
+    /// The sampled YCbCr of `rgb`, encoded with the given luma coefficients and stored in the
+    /// most significant bits of an 8 or 16 bit container.
+    fn encode(
+        (kr, kb): (f32, f32),
+        range: ColorRange,
+        bit_depth: u32,
+        [r, g, b]: [f32; 3],
+    ) -> [f32; 3] {
+        let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+        let pb = (b - y) / (2.0 * (1.0 - kb));
+        let pr = (r - y) / (2.0 * (1.0 - kr));
+        let step = (1u32 << (bit_depth - 8)) as f32;
+        let max_code = ((1u32 << bit_depth) - 1) as f32;
+        let center = (1u32 << (bit_depth - 1)) as f32;
+        let codes = match range {
+            ColorRange::Limited => [
+                (16.0 + 219.0 * y) * step,
+                (128.0 + 224.0 * pb) * step,
+                (128.0 + 224.0 * pr) * step,
+            ],
+            ColorRange::Full => [y * max_code, center + pb * max_code, center + pr * max_code],
+        };
+        let container_depth = if bit_depth > 8 { 16 } else { 8 };
+        let shift = (1u32 << (container_depth - bit_depth)) as f32;
+        let container_max = ((1u32 << container_depth) - 1) as f32;
+        codes.map(|code| code * shift / container_max)
+    }
+
+    #[test]
+    fn ycbcr_reference_colors() {
+        let colors = [
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+        ];
+        for (matrix, coefficients) in [
+            (ColorMatrix::Bt601, (0.299, 0.114)),
+            (ColorMatrix::Bt709, (0.2126, 0.0722)),
+            (ColorMatrix::Bt2020, (0.2627, 0.0593)),
+        ] {
+            for range in [ColorRange::Limited, ColorRange::Full] {
+                for bit_depth in [8, 10] {
+                    let rows = ycbcr_to_rgb_rows(matrix, range, bit_depth);
+                    for rgb in colors {
+                        let [y, cb, cr] = encode(coefficients, range, bit_depth, rgb);
+                        let decoded =
+                            rows.map(|row| row[0] * y + row[1] * cb + row[2] * cr + row[3]);
+                        for (decoded, expected) in decoded.into_iter().zip(rgb) {
+                            assert!(
+                                (decoded - expected).abs() < 1e-4,
+                                "{matrix:?} {range:?} {bit_depth} bit: {rgb:?} decoded as {decoded}"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
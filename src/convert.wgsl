@@ -0,0 +1,69 @@
+// Converts the planes of an imported dmatex into an rgb texture.
+
+struct Params {
+    // rgb = rows * vec4(y, cb, cr, 1.0)
+    rows: array<vec4<f32>, 3>,
+    source_layout: u32,
+    // source texel = transform * vec3(output texel, 1)
+    transform_x: vec4<i32>,
+    transform_y: vec4<i32>,
+}
+
+// keep in sync with `layout_index` in convert.rs
+const LAYOUT_NV12: u32 = 0u;
+const LAYOUT_NV21: u32 = 1u;
+const LAYOUT_YUV420: u32 = 2u;
+const LAYOUT_YUYV: u32 = 3u;
+
+@group(0) @binding(0) var plane0: texture_2d<f32>;
+@group(0) @binding(1) var plane1: texture_2d<f32>;
+@group(0) @binding(2) var plane2: texture_2d<f32>;
+@group(0) @binding(3) var output: texture_storage_2d<OUTPUT_FORMAT, write>;
+@group(0) @binding(4) var<uniform> params: Params;
+
+fn load_ycbcr(pos: vec2<u32>) -> vec3<f32> {
+    let chroma_pos = pos / 2u;
+    switch params.source_layout {
+        case LAYOUT_NV12: {
+            let y = textureLoad(plane0, pos, 0).r;
+            let cbcr = textureLoad(plane1, chroma_pos, 0).rg;
+            return vec3(y, cbcr);
+        }
+        case LAYOUT_NV21: {
+            let y = textureLoad(plane0, pos, 0).r;
+            let crcb = textureLoad(plane1, chroma_pos, 0).rg;
+            return vec3(y, crcb.g, crcb.r);
+        }
+        case LAYOUT_YUV420: {
+            let y = textureLoad(plane0, pos, 0).r;
+            let cb = textureLoad(plane1, chroma_pos, 0).r;
+            let cr = textureLoad(plane2, chroma_pos, 0).r;
+            return vec3(y, cb, cr);
+        }
+        case LAYOUT_YUYV: {
+            let macropixel = textureLoad(plane0, vec2(pos.x / 2u, pos.y), 0);
+            let y = select(macropixel.r, macropixel.b, pos.x % 2u == 1u);
+            return vec3(y, macropixel.g, macropixel.a);
+        }
+        default: {
+            return vec3(0.0);
+        }
+    }
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+    let pos = vec3(vec2<i32>(id.xy), 1);
+    let source_pos = vec2(dot(params.transform_x.xyz, pos), dot(params.transform_y.xyz, pos));
+    let ycbcr = vec4(load_ycbcr(vec2<u32>(source_pos)), 1.0);
+    let rgb = vec3(
+        dot(params.rows[0], ycbcr),
+        dot(params.rows[1], ycbcr),
+        dot(params.rows[2], ycbcr),
+    );
+    textureStore(output, id.xy, vec4(clamp(rgb, vec3(0.0), vec3(1.0)), 1.0));
+}
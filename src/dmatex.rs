@@ -1,27 +1,422 @@
+use std::{
+    fmt::{Debug, Display},
+    os::fd::{AsFd as _, AsRawFd as _, BorrowedFd},
+};
+
+use thiserror::Error;
 use zvariant::{self, OwnedFd};
 
+use crate::modifier::Modifier;
+
 /// Dmabuf Backed Texture
 #[derive(Debug, serde::Serialize, serde::Deserialize, zvariant::Type)]
 pub struct Dmatex {
     pub planes: Vec<DmatexPlane>,
     pub res: Resolution,
     pub format: u32,
-    /// TODO: implement this, or remove it
+    /// the buffer is upside down, it gets flipped while importing (before undoing `transform`)
     pub flip_y: bool,
+    /// the transform that was applied to the buffer contents, undone while importing
+    pub transform: DmatexTransform,
     /// if the format has an srgb version, use that
     pub srgb: bool,
+    /// only used for yuv formats
+    pub color_matrix: ColorMatrix,
+    /// only used for yuv formats
+    pub color_range: ColorRange,
+    /// if not set, the dmabuf has to be ready for sampling once it's handed over
+    pub explicit_sync: Option<DmatexExplicitSync>,
+    /// `dev_t` of the DRM node the dmabuf was allocated on, either the primary or render node.
+    /// Importing fails if it isn't the render device
+    pub device: Option<u64>,
+}
+
+/// Explicit synchronization of a Dmatex through DRM syncobj timelines
+#[derive(Debug, serde::Serialize, serde::Deserialize, zvariant::Type)]
+pub struct DmatexExplicitSync {
+    /// rendering waits for this point before sampling the Dmatex
+    pub acquire: SyncobjPoint,
+    /// signaled once rendering no longer uses the Dmatex
+    pub release: SyncobjPoint,
+}
+
+/// A point on a DRM syncobj timeline
+#[derive(Debug, serde::Serialize, serde::Deserialize, zvariant::Type)]
+pub struct SyncobjPoint {
+    /// the syncobj, as exported by `drmSyncobjHandleToFD`
+    pub syncobj_fd: OwnedFd,
+    pub point: u64,
+}
+
+/// Matches `wl_output.transform`, rotations are counter-clockwise and flips are around the
+/// vertical axis, applied before rotating
+#[derive(
+    Debug, Default, serde::Serialize, serde::Deserialize, zvariant::Type, Copy, Clone, PartialEq, Eq,
+)]
+pub enum DmatexTransform {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    Flipped,
+    Flipped90,
+    Flipped180,
+    Flipped270,
+}
+
+impl DmatexTransform {
+    /// Whether the upright image has the width and height of the buffer swapped.
+    pub fn swaps_dimensions(self) -> bool {
+        matches!(
+            self,
+            Self::Rotate90 | Self::Rotate270 | Self::Flipped90 | Self::Flipped270
+        )
+    }
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, zvariant::Type, Copy, Clone)]
+/// The YCbCr to RGB conversion matrix of a yuv Dmatex
+#[derive(
+    Debug, Default, serde::Serialize, serde::Deserialize, zvariant::Type, Copy, Clone, PartialEq, Eq,
+)]
+pub enum ColorMatrix {
+    Bt601,
+    #[default]
+    Bt709,
+    Bt2020,
+}
+
+/// The quantization range of a yuv Dmatex
+#[derive(
+    Debug, Default, serde::Serialize, serde::Deserialize, zvariant::Type, Copy, Clone, PartialEq, Eq,
+)]
+pub enum ColorRange {
+    /// Y in 16..=235, CbCr in 16..=240 (scaled for higher bit depths)
+    #[default]
+    Limited,
+    Full,
+}
+
+#[derive(
+    Debug, serde::Serialize, serde::Deserialize, zvariant::Type, Copy, Clone, PartialEq, Eq,
+)]
 pub struct Resolution {
     pub x: u32,
     pub y: u32,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, zvariant::Type)]
+#[derive(serde::Serialize, serde::Deserialize, zvariant::Type)]
 pub struct DmatexPlane {
     pub dmabuf_fd: OwnedFd,
     pub modifier: u64,
     pub offset: u32,
     pub stride: i32,
 }
+
+impl Debug for DmatexPlane {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DmatexPlane")
+            .field("dmabuf_fd", &self.dmabuf_fd)
+            .field("modifier", &Modifier(self.modifier))
+            .field("offset", &self.offset)
+            .field("stride", &self.stride)
+            .finish()
+    }
+}
+
+/// DRM buffers have at most 4 planes.
+pub const MAX_PLANES: usize = 4;
+
+impl Dmatex {
+    /// Checks that the dmatex describes a valid DRM buffer, reporting every violation found.
+    pub fn validate(&self) -> Result<(), DmatexValidationError> {
+        let mut violations = Vec::new();
+        if self.res.x == 0 || self.res.y == 0 {
+            violations.push(DmatexViolation::ZeroResolution(self.res));
+        }
+        if self.planes.is_empty() {
+            violations.push(DmatexViolation::NoPlanes);
+        }
+        if self.planes.len() > MAX_PLANES {
+            violations.push(DmatexViolation::TooManyPlanes(self.planes.len()));
+        }
+        let ids = self
+            .planes
+            .iter()
+            .map(|plane| DmabufId::of(plane.dmabuf_fd.as_fd()))
+            .collect::<Vec<_>>();
+        for (i, plane) in self.planes.iter().enumerate() {
+            if let Some(first) = self.planes.first()
+                && plane.modifier != first.modifier
+            {
+                violations.push(DmatexViolation::MixedModifiers {
+                    plane: i,
+                    modifier: Modifier(plane.modifier),
+                    expected: Modifier(first.modifier),
+                });
+            }
+            if plane.stride <= 0 {
+                violations.push(DmatexViolation::InvalidStride {
+                    plane: i,
+                    stride: plane.stride,
+                });
+            }
+            if let Some(first) =
+                (0..i).find(|&j| ids[j] == ids[i] && self.planes[j].offset == plane.offset)
+            {
+                violations.push(DmatexViolation::DuplicateOffset {
+                    first,
+                    second: i,
+                    offset: plane.offset,
+                });
+            }
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(DmatexValidationError(violations))
+        }
+    }
+}
+
+/// Identifies the dmabuf behind an fd, fds duplicated from each other share it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum DmabufId {
+    Inode {
+        dev: u64,
+        ino: u64,
+    },
+    /// fstat failed, only the fd itself is known
+    Fd(i32),
+}
+
+impl DmabufId {
+    pub(crate) fn of(fd: BorrowedFd) -> Self {
+        let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+        if unsafe { libc::fstat(fd.as_raw_fd(), stat.as_mut_ptr()) } != 0 {
+            return Self::Fd(fd.as_raw_fd());
+        }
+        let stat = unsafe { stat.assume_init() };
+        Self::Inode {
+            dev: stat.st_dev,
+            ino: stat.st_ino,
+        }
+    }
+}
+
+/// A single problem found by [`Dmatex::validate`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DmatexViolation {
+    #[error("Resolution {}x{} is empty", .0.x, .0.y)]
+    ZeroResolution(Resolution),
+    #[error("No planes")]
+    NoPlanes,
+    #[error("{0} planes, but DRM buffers have at most {MAX_PLANES}")]
+    TooManyPlanes(usize),
+    #[error("Plane {plane} uses modifier {modifier}, but plane 0 uses {expected}")]
+    MixedModifiers {
+        plane: usize,
+        modifier: Modifier,
+        expected: Modifier,
+    },
+    #[error("Plane {plane} has the non positive stride {stride}")]
+    InvalidStride { plane: usize, stride: i32 },
+    #[error("Planes {first} and {second} start at the same offset {offset} of the same dmabuf")]
+    DuplicateOffset {
+        first: usize,
+        second: usize,
+        offset: u32,
+    },
+}
+
+/// Every violation found by [`Dmatex::validate`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub struct DmatexValidationError(pub Vec<DmatexViolation>);
+
+impl Display for DmatexValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid dmatex: ")?;
+        for (i, violation) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use drm_fourcc::{DrmFourcc, DrmModifier};
+
+    /// An fd of a new, empty memfd, each with its own inode.
+    fn memfd() -> OwnedFd {
+        let fd = unsafe { libc::memfd_create(c"dmatex".as_ptr(), 0) };
+        assert!(fd >= 0);
+        unsafe { <std::os::fd::OwnedFd as std::os::fd::FromRawFd>::from_raw_fd(fd) }.into()
+    }
+
+    /// Another fd of the same memfd.
+    fn dup(fd: &OwnedFd) -> OwnedFd {
+        fd.as_fd().try_clone_to_owned().unwrap().into()
+    }
+
+    fn plane(dmabuf_fd: OwnedFd, offset: u32) -> DmatexPlane {
+        DmatexPlane {
+            dmabuf_fd,
+            modifier: DrmModifier::Linear.into(),
+            offset,
+            stride: 256,
+        }
+    }
+
+    fn dmatex(planes: Vec<DmatexPlane>) -> Dmatex {
+        Dmatex {
+            planes,
+            res: Resolution { x: 64, y: 64 },
+            format: DrmFourcc::Nv12 as u32,
+            flip_y: false,
+            transform: DmatexTransform::Normal,
+            srgb: false,
+            color_matrix: ColorMatrix::default(),
+            color_range: ColorRange::default(),
+            explicit_sync: None,
+            device: None,
+        }
+    }
+
+    fn violations(buf: &Dmatex) -> Vec<DmatexViolation> {
+        buf.validate().err().map(|err| err.0).unwrap_or_default()
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(dmatex(vec![plane(memfd(), 0)]).validate(), Ok(()));
+        // planes sharing a dmabuf at different offsets
+        let fd = memfd();
+        let second = dup(&fd);
+        let buf = dmatex(vec![plane(fd, 0), plane(second, 64 * 256)]);
+        assert_eq!(buf.validate(), Ok(()));
+    }
+
+    #[test]
+    fn zero_resolution() {
+        let mut buf = dmatex(vec![plane(memfd(), 0)]);
+        buf.res.y = 0;
+        assert_eq!(
+            violations(&buf),
+            [DmatexViolation::ZeroResolution(Resolution { x: 64, y: 0 })]
+        );
+    }
+
+    #[test]
+    fn no_planes() {
+        assert_eq!(violations(&dmatex(vec![])), [DmatexViolation::NoPlanes]);
+    }
+
+    #[test]
+    fn too_many_planes() {
+        let planes = (0..5).map(|_| plane(memfd(), 0)).collect();
+        assert_eq!(
+            violations(&dmatex(planes)),
+            [DmatexViolation::TooManyPlanes(5)]
+        );
+    }
+
+    #[test]
+    fn mixed_modifiers() {
+        let mut second = plane(memfd(), 0);
+        second.modifier = DrmModifier::I915_x_tiled.into();
+        assert_eq!(
+            violations(&dmatex(vec![plane(memfd(), 0), second])),
+            [DmatexViolation::MixedModifiers {
+                plane: 1,
+                modifier: Modifier(DrmModifier::I915_x_tiled.into()),
+                expected: Modifier(DrmModifier::Linear.into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_stride() {
+        let mut first = plane(memfd(), 0);
+        first.stride = 0;
+        let mut second = plane(memfd(), 0);
+        second.stride = -256;
+        assert_eq!(
+            violations(&dmatex(vec![first, second])),
+            [
+                DmatexViolation::InvalidStride {
+                    plane: 0,
+                    stride: 0
+                },
+                DmatexViolation::InvalidStride {
+                    plane: 1,
+                    stride: -256
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_offset() {
+        let fd = memfd();
+        let second = dup(&fd);
+        assert_eq!(
+            violations(&dmatex(vec![plane(fd, 128), plane(second, 128)])),
+            [DmatexViolation::DuplicateOffset {
+                first: 0,
+                second: 1,
+                offset: 128,
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let fd = memfd();
+        let mut second = plane(dup(&fd), 0);
+        second.modifier = DrmModifier::I915_x_tiled.into();
+        second.stride = 0;
+        let mut buf = dmatex(vec![
+            plane(fd, 0),
+            second,
+            plane(memfd(), 0),
+            plane(memfd(), 0),
+            plane(memfd(), 0),
+        ]);
+        buf.res = Resolution { x: 0, y: 0 };
+        let err = buf.validate().unwrap_err();
+        assert_eq!(
+            err.0,
+            [
+                DmatexViolation::ZeroResolution(Resolution { x: 0, y: 0 }),
+                DmatexViolation::TooManyPlanes(5),
+                DmatexViolation::MixedModifiers {
+                    plane: 1,
+                    modifier: Modifier(DrmModifier::I915_x_tiled.into()),
+                    expected: Modifier(DrmModifier::Linear.into()),
+                },
+                DmatexViolation::InvalidStride {
+                    plane: 1,
+                    stride: 0
+                },
+                DmatexViolation::DuplicateOffset {
+                    first: 0,
+                    second: 1,
+                    offset: 0,
+                },
+            ]
+        );
+        assert_eq!(
+            err.to_string(),
+            "Invalid dmatex: Resolution 0x0 is empty; 5 planes, but DRM buffers have at most 4; \
+             Plane 1 uses modifier I915_FORMAT_MOD_X_TILED, \
+             but plane 0 uses DRM_FORMAT_MOD_LINEAR; \
+             Plane 1 has the non positive stride 0; \
+             Planes 0 and 1 start at the same offset 0 of the same dmabuf"
+        );
+    }
+}
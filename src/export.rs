@@ -1,191 +1,453 @@
-use std::{borrow::Cow, ffi::c_void, mem};
+#![warn(clippy::unwrap_used, clippy::expect_used)]
+use std::{
+    os::fd::{FromRawFd as _, OwnedFd},
+    sync::{Arc, Mutex},
+};
 
 use ash::vk;
 use bevy::{
-    prelude::*,
+    app::{App, Plugin, PostUpdate},
+    asset::{AssetEvent, AssetEvents, Handle},
+    ecs::{
+        event::EventReader,
+        resource::Resource,
+        schedule::IntoScheduleConfigs as _,
+        system::{Res, ResMut},
+    },
+    image::Image,
+    pbr::{PreparedMaterial, StandardMaterial},
+    platform::collections::HashMap,
     render::{
-        render_asset::RenderAssets,
-        render_graph::{RenderLabel, ViewNode},
-        render_resource::{
-            CachedComputePipelineId, ComputePipelineDescriptor, PipelineCache,
-            RenderPipelineDescriptor,
-        },
+        Render, RenderApp, RenderSet,
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_asset::{RenderAssets, prepare_assets},
+        render_resource::{Texture, TextureView},
         renderer::RenderDevice,
         texture::GpuImage,
-        view::ViewTarget,
     },
+    utils::default,
+};
+use thiserror::Error;
+use tracing::{debug, error, warn};
+use wgpu::{
+    TextureViewDescriptor,
+    hal::{MemoryFlags, TextureDescriptor, vulkan::Api as Vulkan},
 };
-use wgpu::hal::{Device, api::Vulkan};
+
+use crate::{
+    capabilities::drm_device,
+    dmatex::{Dmatex, DmatexPlane, Resolution},
+    format_mapping::{get_drm_modifiers, wgpu_to_fourcc, wgpu_to_vk_format},
+    import::{get_image_format_properties, hal_texture_uses, vk_image_usage},
+};
+
 pub struct DmabufExportPlugin;
 
 impl Plugin for DmabufExportPlugin {
     fn build(&self, app: &mut App) {
-        todo!()
+        app.insert_resource(ExportedDmatexs(default()));
+        app.add_plugins(ExtractResourcePlugin::<ExportedDmatexs>::default());
+        app.add_systems(PostUpdate, remove_dropped_exports.after(AssetEvents));
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_systems(
+                Render,
+                export_gpu_images
+                    .in_set(RenderSet::PrepareAssets)
+                    .after(prepare_assets::<GpuImage>)
+                    .before(prepare_assets::<PreparedMaterial<StandardMaterial>>),
+            );
+        } else {
+            warn!("unable to init dmabuf exporting!");
+        }
+    }
+}
+
+/// Images that are backed by an exported dmabuf, like the [`RenderTarget::Image`] of a camera.
+///
+/// [`RenderTarget::Image`]: bevy::render::camera::RenderTarget::Image
+#[derive(Resource, Clone, ExtractResource)]
+pub struct ExportedDmatexs(Arc<Mutex<HashMap<Handle<Image>, DmaExport>>>);
+
+#[derive(Debug)]
+enum DmaExport {
+    Requested,
+    Exported(ExportedTexture, Option<Dmatex>),
+    Failed,
+}
+
+impl ExportedDmatexs {
+    /// Replaces the gpu texture of `handle` with a dmabuf backed texture once the image has been
+    /// prepared by the render world, the [`Dmatex`] can then be retrieved with
+    /// [`ExportedDmatexs::take_dmatex`].
+    pub fn export(&self, handle: &Handle<Image>) {
+        #[expect(clippy::unwrap_used)]
+        self.0
+            .lock()
+            .unwrap()
+            .entry(handle.clone_weak())
+            .or_insert(DmaExport::Requested);
+    }
+    /// Takes the [`Dmatex`] of an exported image, returns [`None`] if the image hasn't been
+    /// exported yet or the Dmatex has already been taken. Resizing the image exports a new
+    /// Dmatex.
+    pub fn take_dmatex(&self, handle: &Handle<Image>) -> Option<Dmatex> {
+        #[expect(clippy::unwrap_used)]
+        match self.0.lock().unwrap().get_mut(handle)? {
+            DmaExport::Exported(_, dmatex) => dmatex.take(),
+            DmaExport::Requested | DmaExport::Failed => None,
+        }
+    }
+    /// Stops exporting `handle`, the dmabuf is freed once the render world stops using it.
+    pub fn remove(&self, handle: &Handle<Image>) {
+        #[expect(clippy::unwrap_used)]
+        self.0.lock().unwrap().remove(handle);
     }
 }
 
-fn create_exported_texture(
+#[derive(Clone, Debug)]
+struct ExportedTexture {
+    texture: Texture,
+    texture_view: TextureView,
+}
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("Format has no DRM fourcc equivalent")]
+    UnsupportedFormat,
+    #[error("RenderDevice is not a Vulkan Device")]
+    NotVulkan,
+    #[error("No exportable DRM format modifier for this format and usage")]
+    NoValidModifier,
+    #[error("Unable to find valid Gpu Memory type index")]
+    NoValidMemoryTypes,
+    #[error("Unable to duplicate dmabuf fd: {0}")]
+    DuplicateFd(std::io::Error),
+    #[error("Vulkan Error: {0}")]
+    Vulkan(#[from] vk::Result),
+}
+
+/// Frees the exports of images whose asset was removed.
+fn remove_dropped_exports(
+    mut events: EventReader<AssetEvent<Image>>,
+    exported: Res<ExportedDmatexs>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Removed { id } = event {
+            #[expect(clippy::unwrap_used)]
+            exported.0.lock().unwrap().remove(&Handle::Weak(*id));
+        }
+    }
+}
+
+fn export_gpu_images(
+    mut gpu_images: ResMut<RenderAssets<GpuImage>>,
+    exported: Res<ExportedDmatexs>,
+    device: Res<RenderDevice>,
+) {
+    #[expect(clippy::unwrap_used)]
+    let mut exported = exported.0.lock().unwrap();
+    let handles = exported.keys().cloned().collect::<Vec<_>>();
+    for handle in handles {
+        let Some(gpu_image) = gpu_images.get_mut(&handle) else {
+            // not prepared yet
+            continue;
+        };
+        let outdated = match exported.get(&handle) {
+            Some(DmaExport::Requested) => true,
+            // a resized image gets a new, unexported texture
+            Some(DmaExport::Exported(tex, _)) => tex.texture.size() != gpu_image.size,
+            _ => false,
+        };
+        if outdated {
+            match export_texture(&device, gpu_image) {
+                Ok((tex, dmatex)) => {
+                    debug!("exported dmatex");
+                    exported.insert(handle.clone(), DmaExport::Exported(tex, Some(dmatex)));
+                }
+                Err(err) => {
+                    error!("failed to export dmatex: {err}");
+                    exported.insert(handle.clone(), DmaExport::Failed);
+                    continue;
+                }
+            }
+        }
+        if let Some(DmaExport::Exported(tex, _)) = exported.get(&handle) {
+            gpu_image.texture_view = tex.texture_view.clone();
+            gpu_image.texture = tex.texture.clone();
+        }
+    }
+}
+
+/// Allocates an exportable, DRM format modifier tiled texture matching `gpu_image`.
+#[tracing::instrument(level = "debug", skip_all)]
+fn export_texture(
     device: &RenderDevice,
-    desc: &wgpu::hal::TextureDescriptor,
-    // w: RenderAssets<>
-) -> wgpu::Texture {
-    unsafe {
+    gpu_image: &GpuImage,
+) -> Result<(ExportedTexture, Dmatex), ExportError> {
+    let format = gpu_image.texture_format;
+    let (Some(vk_format), Some(fourcc)) = (wgpu_to_vk_format(format), wgpu_to_fourcc(format))
+    else {
+        return Err(ExportError::UnsupportedFormat);
+    };
+    let wgpu_desc = wgpu::TextureDescriptor {
+        label: None,
+        size: gpu_image.size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: gpu_image.texture.usage(),
+        view_formats: &[],
+    };
+
+    let (exported, drm_device) = unsafe {
         device
             .wgpu_device()
-            .as_hal::<Vulkan, _, _>(|dev| -> Option<()> {
-                let mem = dev
-                    .unwrap()
-                    .raw_device()
-                    .allocate_memory(
-                        &vk::MemoryAllocateInfo {
-                            // allocation_size: desc,
-                            memory_type_index: todo!(),
-                            ..Default::default()
-                        },
-                        None,
-                    )
-                    .ok()?;
-            });
-    }
-    todo!()
+            .as_hal::<Vulkan, _, _>(|dev| -> Result<_, ExportError> {
+                let dev = dev.ok_or(ExportError::NotVulkan)?;
+                let exported = create_exportable_image(dev, vk_format, &wgpu_desc)?;
+                Ok((exported, drm_device(dev)))
+            })
+    }?;
+
+    let res = Resolution {
+        x: wgpu_desc.size.width,
+        y: wgpu_desc.size.height,
+    };
+    let planes = exported
+        .planes
+        .iter()
+        .map(|layout| {
+            Ok(DmatexPlane {
+                dmabuf_fd: exported.fd.try_clone()?.into(),
+                modifier: exported.modifier,
+                offset: layout.offset as u32,
+                stride: layout.row_pitch as i32,
+            })
+        })
+        .collect::<Result<Vec<_>, std::io::Error>>();
+
+    let ExportedImage {
+        vk_dev, image, mem, ..
+    } = exported;
+    let descriptor = TextureDescriptor {
+        label: None,
+        size: wgpu_desc.size,
+        mip_level_count: wgpu_desc.mip_level_count,
+        sample_count: wgpu_desc.sample_count,
+        dimension: wgpu_desc.dimension,
+        format,
+        usage: hal_texture_uses(wgpu_desc.usage),
+        memory_flags: MemoryFlags::empty(),
+        view_formats: vec![],
+    };
+    let hal_texture = unsafe {
+        wgpu::hal::vulkan::Device::texture_from_raw(
+            image,
+            &descriptor,
+            Some(Box::new(move || {
+                vk_dev.destroy_image(image, None);
+                vk_dev.free_memory(mem, None);
+            })),
+        )
+    };
+    let texture = Texture::from(unsafe {
+        device
+            .wgpu_device()
+            .create_texture_from_hal::<Vulkan>(hal_texture, &wgpu_desc)
+    });
+    // checked after wrapping the image so the texture cleans it up on failure
+    let planes = planes.map_err(ExportError::DuplicateFd)?;
+    let texture_view = texture.create_view(&TextureViewDescriptor::default());
+
+    Ok((
+        ExportedTexture {
+            texture,
+            texture_view,
+        },
+        Dmatex {
+            planes,
+            res,
+            format: fourcc,
+            flip_y: false,
+            transform: default(),
+            srgb: format.is_srgb(),
+            color_matrix: default(),
+            color_range: default(),
+            explicit_sync: None,
+            device: drm_device.and_then(|drm| drm.render.or(drm.primary)),
+        },
+    ))
 }
 
-fn create_texture(dev: &wgpu::hal::vulkan::Device, desc: &wgpu::hal::TextureDescriptor) {}
-
-fn import_texture(device: &RenderDevice) -> wgpu::Texture {
-    let w = unsafe {
-        device.wgpu_device().as_hal::<Vulkan, _, _>(|dev| {
-            let dev = dev.unwrap();
-            let mem_properties = {
-                unsafe {
-                    dev.shared_instance()
-                        .raw_instance()
-                        .get_physical_device_memory_properties(dev.raw_physical_device())
-                }
+struct ExportedImage {
+    vk_dev: ash::Device,
+    image: vk::Image,
+    mem: vk::DeviceMemory,
+    fd: OwnedFd,
+    modifier: u64,
+    planes: Vec<vk::SubresourceLayout>,
+}
+
+unsafe fn create_exportable_image(
+    dev: &wgpu::hal::vulkan::Device,
+    format: vk::Format,
+    desc: &wgpu::TextureDescriptor,
+) -> Result<ExportedImage, ExportError> {
+    let instance = dev.shared_instance().raw_instance();
+    let phys_dev = dev.raw_physical_device();
+    let vk_dev = dev.raw_device();
+    let usage = vk_image_usage(desc.usage);
+    let extent = vk::Extent3D {
+        width: desc.size.width,
+        height: desc.size.height,
+        depth: 1,
+    };
+
+    // let the driver pick the best modifier out of every one that can be exported with this usage
+    let modifiers = get_drm_modifiers(instance, phys_dev, format)
+        .1
+        .into_iter()
+        .map(|props| props.drm_format_modifier)
+        .filter(|&modifier| {
+            let Ok((props, external_props)) = (unsafe {
+                get_image_format_properties(instance, phys_dev, format, &[], modifier, usage)
+            }) else {
+                return false;
             };
-            let memory_types = &mem_properties.memory_types_as_slice();
-            let valid_ash_memory_types =
-                memory_types
-                    .iter()
-                    .enumerate()
-                    .fold(u32::MAX, |u, (i, mem)| {
-                        if (vk::MemoryPropertyFlags::DEVICE_LOCAL
-                            | vk::MemoryPropertyFlags::HOST_VISIBLE
-                            | vk::MemoryPropertyFlags::HOST_COHERENT
-                            | vk::MemoryPropertyFlags::HOST_CACHED
-                            | vk::MemoryPropertyFlags::LAZILY_ALLOCATED)
-                            .contains(mem.property_flags)
-                        {
-                            u | (1 << i)
-                        } else {
-                            u
-                        }
-                    });
+            external_props
+                .external_memory_features
+                .contains(vk::ExternalMemoryFeatureFlags::EXPORTABLE)
+                && extent.width <= props.max_extent.width
+                && extent.height <= props.max_extent.height
         })
-    };
-    unsafe {
-        wgpu::hal::vulkan::Device::texture_from_raw(todo!(), todo!(), todo!());
+        .collect::<Vec<_>>();
+    if modifiers.is_empty() {
+        return Err(ExportError::NoValidModifier);
+    }
+
+    let mut modifier_info =
+        vk::ImageDrmFormatModifierListCreateInfoEXT::default().drm_format_modifiers(&modifiers);
+    let mut external_info = vk::ExternalMemoryImageCreateInfo::default()
+        .handle_types(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT);
+    let image_info = vk::ImageCreateInfo::default()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(format)
+        .extent(extent)
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT)
+        .usage(usage)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .push_next(&mut modifier_info)
+        .push_next(&mut external_info);
+    let image = unsafe { vk_dev.create_image(&image_info, None) }?;
+
+    match unsafe { allocate_exported_memory(dev, image, format) } {
+        Ok((mem, fd, modifier, planes)) => Ok(ExportedImage {
+            vk_dev: vk_dev.clone(),
+            image,
+            mem,
+            fd,
+            modifier,
+            planes,
+        }),
+        Err(err) => {
+            unsafe { vk_dev.destroy_image(image, None) };
+            Err(err)
+        }
     }
-    todo!()
 }
 
-// #[derive(Debug, Clone, Component)]
-// struct ExportedDmabufTarget {
-//     image: Handle<Image>,
-//     dmabuf_texture: GpuImage,
-// }
-//
-// #[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
-// struct ExportedDmabufCopyLabel;
-//
-// #[derive(Default)]
-// struct ExportedDmabufCopyNode;
-//
-// impl ViewNode for ExportedDmabufCopyNode {
-//     type ViewQuery = (&'static ViewTarget, &'static ExportedDmabufTarget);
-//
-//     fn run<'w>(
-//         &self,
-//         graph: &mut bevy::render::render_graph::RenderGraphContext,
-//         render_context: &mut bevy::render::renderer::RenderContext<'w>,
-//         (view_target, dmabuf_target): bevy::ecs::query::QueryItem<'w, Self::ViewQuery>,
-//         world: &'w World,
-//     ) -> Result<(), bevy::render::render_graph::NodeRunError> {
-//         // view_target.out_texture()
-//
-//         Ok(())
-//     }
-// }
-// #[derive(Resource)]
-// struct ExportedDmabufCopyPipeline {
-//     pipeline: CachedComputePipelineId,
-// }
-// impl FromWorld for ExportedDmabufCopyPipeline {
-//     fn from_world(world: &mut World) -> Self {
-//         let render_device = world.resource::<RenderDevice>();
-//         let pipeline_cache = world.resource::<PipelineCache>();
-//         pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
-//             label: (),
-//             layout: (),
-//             push_constant_ranges: (),
-//             shader: (),
-//             shader_defs: (),
-//             entry_point: (),
-//             zero_initialize_workgroup_memory: false,
-//         });
-//         todo!()
-//     }
-// }
-
-// pub fn export_dma_buf(
-//     handle: &Handle<Image>,
-//     assets: &RenderAssets<GpuImage>,
-//     device: &wgpu::Device,
-//     instance: &wgpu::Instance,
-// ) {
-//     let image = assets.get(handle).unwrap();
-//     unsafe {
-//         image
-//             .texture
-//             .as_hal::<Vulkan, _, _>(|texture| -> Option<()> {
-//                 let texture = texture?;
-//                 let instance = instance.as_hal::<Vulkan>()?;
-//                 device.as_hal::<Vulkan, _, _>(|dev| -> Option<()> {
-//                     let dev = dev?;
-//                     let get_mem_fd = instance
-//                         .shared_instance()
-//                         .raw_instance()
-//                         .get_device_proc_addr(
-//                             dev.raw_device().handle(),
-//                             c"vkGetMemoryFdKHR".as_ptr(),
-//                         )?;
-//                     let get_mem_fd = unsafe {
-//                         mem::transmute::<
-//                             unsafe extern "system" fn(),
-//                             unsafe extern "system" fn(vk::Device, &VkMemoryGetFdInfoKHR, *mut u32),
-//                         >(get_mem_fd)
-//                     };
-//                     unsafe {
-//                         let fd = 0u32;
-//                         let w = vk::MemoryGetFdInfoKHR {
-//                             memory: todo!(),
-//                             handle_type: vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
-//                             ..default()
-//                         };
-//                     }
-//                     Some(())
-//                 });
-//
-//                 Some(())
-//             });
-//     }
-// }
-//
-// // Provided by VK_KHR_external_memory_fd
-// #[repr(C)]
-// struct VkMemoryGetFdInfoKHR {
-//     s_type: vk::StructureType,
-//     p_next: *const c_void,
-//     memory: vk::DeviceMemory,
-//     handle_type: vk::ExternalMemoryHandleTypeFlags,
-// }
+/// Allocates and binds exportable memory for `image`, then exports it as a dmabuf fd together
+/// with the modifier and memory plane layouts the driver chose.
+unsafe fn allocate_exported_memory(
+    dev: &wgpu::hal::vulkan::Device,
+    image: vk::Image,
+    format: vk::Format,
+) -> Result<(vk::DeviceMemory, OwnedFd, u64, Vec<vk::SubresourceLayout>), ExportError> {
+    let instance = dev.shared_instance().raw_instance();
+    let vk_dev = dev.raw_device();
+
+    let requirements = unsafe { vk_dev.get_image_memory_requirements(image) };
+    let mem_properties =
+        unsafe { instance.get_physical_device_memory_properties(dev.raw_physical_device()) };
+    let memory_type_index = mem_properties
+        .memory_types_as_slice()
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| requirements.memory_type_bits & (1 << i) != 0)
+        .min_by_key(|(_, mem_type)| {
+            !mem_type
+                .property_flags
+                .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        })
+        .map(|(i, _)| i as u32)
+        .ok_or(ExportError::NoValidMemoryTypes)?;
+
+    let mut export_info = vk::ExportMemoryAllocateInfo::default()
+        .handle_types(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT);
+    let mut dedicated_info = vk::MemoryDedicatedAllocateInfo::default().image(image);
+    let alloc_info = vk::MemoryAllocateInfo::default()
+        .allocation_size(requirements.size)
+        .memory_type_index(memory_type_index)
+        .push_next(&mut export_info)
+        .push_next(&mut dedicated_info);
+    let mem = unsafe { vk_dev.allocate_memory(&alloc_info, None) }?;
+
+    let result = (|| {
+        unsafe { vk_dev.bind_image_memory(image, mem, 0) }?;
+        let memory_fd = ash::khr::external_memory_fd::Device::new(instance, vk_dev);
+        let fd = unsafe {
+            memory_fd.get_memory_fd(
+                &vk::MemoryGetFdInfoKHR::default()
+                    .memory(mem)
+                    .handle_type(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT),
+            )
+        }?;
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let drm_modifier = ash::ext::image_drm_format_modifier::Device::new(instance, vk_dev);
+        let mut modifier_props = vk::ImageDrmFormatModifierPropertiesEXT::default();
+        unsafe {
+            drm_modifier.get_image_drm_format_modifier_properties(image, &mut modifier_props)
+        }?;
+        let modifier = modifier_props.drm_format_modifier;
+        let plane_count = get_drm_modifiers(instance, dev.raw_physical_device(), format)
+            .1
+            .into_iter()
+            .find(|props| props.drm_format_modifier == modifier)
+            .map_or(1, |props| props.drm_format_modifier_plane_count);
+        let planes = (0..plane_count)
+            .map(|i| unsafe {
+                vk_dev.get_image_subresource_layout(
+                    image,
+                    vk::ImageSubresource {
+                        aspect_mask: memory_plane_aspect(i),
+                        mip_level: 0,
+                        array_layer: 0,
+                    },
+                )
+            })
+            .collect();
+        Ok((fd, modifier, planes))
+    })();
+    match result {
+        Ok((fd, modifier, planes)) => Ok((mem, fd, modifier, planes)),
+        Err(err) => {
+            unsafe { vk_dev.free_memory(mem, None) };
+            Err(err)
+        }
+    }
+}
+
+fn memory_plane_aspect(plane: u32) -> vk::ImageAspectFlags {
+    match plane {
+        0 => vk::ImageAspectFlags::MEMORY_PLANE_0_EXT,
+        1 => vk::ImageAspectFlags::MEMORY_PLANE_1_EXT,
+        2 => vk::ImageAspectFlags::MEMORY_PLANE_2_EXT,
+        _ => vk::ImageAspectFlags::MEMORY_PLANE_3_EXT,
+    }
+}
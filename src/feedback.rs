@@ -0,0 +1,200 @@
+use std::{
+    fs::File,
+    io::{self, Write as _},
+    os::fd::{AsRawFd as _, FromRawFd as _, OwnedFd},
+};
+
+use crate::capabilities::DmabufCapabilities;
+
+/// Size of a single format table entry in bytes.
+pub const FORMAT_TABLE_ENTRY_SIZE: usize = 16;
+
+/// `zwp_linux_dmabuf_feedback_v1.tranche_flags.scanout`
+pub const TRANCHE_FLAG_SCANOUT: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, zvariant::Type)]
+pub struct DmabufFeedback {
+    /// `dev_t` of the device bevy renders on, clients should allocate on it
+    pub main_device: u64,
+    pub format_table: Vec<FormatTableEntry>,
+    /// in order of preference
+    pub tranches: Vec<DmabufTranche>,
+}
+
+/// A fourcc and modifier pair of the format table.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, zvariant::Type,
+)]
+pub struct FormatTableEntry {
+    pub format: u32,
+    pub modifier: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, zvariant::Type)]
+pub struct DmabufTranche {
+    /// `dev_t` of the device the buffers of this tranche are accessed on
+    pub target_device: u64,
+    /// indices into the format table
+    pub formats: Vec<u16>,
+    pub flags: u32,
+}
+
+impl DmabufCapabilities {
+    /// Builds the feedback for the render device, with every importable fourcc and modifier in
+    /// a single tranche. Returns [`None`] if the main device is unknown.
+    pub fn feedback(&self) -> Option<DmabufFeedback> {
+        let main_device = self.main_device?;
+        let format_table = self
+            .formats
+            .iter()
+            .flat_map(|format| {
+                format.modifiers.iter().map(|modifier| FormatTableEntry {
+                    format: format.fourcc,
+                    modifier: modifier.modifier,
+                })
+            })
+            // indices are u16 in the protocol
+            .take(u16::MAX as usize + 1)
+            .collect::<Vec<_>>();
+        let tranche = DmabufTranche {
+            target_device: main_device,
+            formats: (0..format_table.len()).map(|i| i as u16).collect(),
+            flags: 0,
+        };
+        Some(DmabufFeedback {
+            main_device,
+            format_table,
+            tranches: vec![tranche],
+        })
+    }
+}
+
+impl DmabufFeedback {
+    /// The format table in the binary layout of the protocol: per entry a native endian u32
+    /// fourcc, 4 bytes of padding and a native endian u64 modifier.
+    pub fn format_table_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.format_table.len() * FORMAT_TABLE_ENTRY_SIZE);
+        for entry in &self.format_table {
+            bytes.extend_from_slice(&entry.format.to_ne_bytes());
+            bytes.extend_from_slice(&[0; 4]);
+            bytes.extend_from_slice(&entry.modifier.to_ne_bytes());
+        }
+        bytes
+    }
+
+    /// Writes the format table into a sealed memfd, ready to be sent as the `format_table` fd.
+    /// Returns the fd and the size of the table.
+    pub fn format_table_memfd(&self) -> io::Result<(OwnedFd, u32)> {
+        let bytes = self.format_table_bytes();
+        let fd = unsafe {
+            libc::memfd_create(
+                c"bevy-dmabuf-format-table".as_ptr(),
+                libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+        file.write_all(&bytes)?;
+        // clients map the table read only, make sure it can't change underneath them
+        let seals =
+            libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+        if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, seals) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((file.into(), bytes.len() as u32))
+    }
+}
+
+/// The native endian byte representation of a `dev_t`, as sent in `main_device` and
+/// `tranche_target_device`.
+pub fn dev_t_bytes(dev: u64) -> [u8; 8] {
+    dev.to_ne_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        capabilities::{FormatCapabilities, ModifierCapabilities},
+        dmatex::Resolution,
+        format_mapping::wgpu_fourccs,
+    };
+    use drm_fourcc::{DrmFourcc, DrmModifier};
+
+    #[test]
+    fn feedback_lists_yuv_formats() {
+        let capabilities = DmabufCapabilities {
+            main_device: Some(1),
+            formats: wgpu_fourccs()
+                .map(|fourcc| FormatCapabilities {
+                    fourcc,
+                    modifiers: vec![ModifierCapabilities {
+                        modifier: DrmModifier::Linear.into(),
+                        plane_count: 1,
+                        max_extent: Resolution { x: 1, y: 1 },
+                    }],
+                })
+                .collect(),
+        };
+        let feedback = capabilities.feedback().unwrap();
+        for fourcc in [
+            DrmFourcc::Nv12,
+            DrmFourcc::P010,
+            DrmFourcc::Yuv420,
+            DrmFourcc::Yuyv,
+        ] {
+            assert!(
+                feedback
+                    .format_table
+                    .iter()
+                    .any(|entry| entry.format == fourcc as u32),
+                "{fourcc}"
+            );
+        }
+        assert_eq!(
+            feedback.tranches[0].formats.len(),
+            feedback.format_table.len()
+        );
+    }
+
+    #[test]
+    fn format_table_layout() {
+        let format_table = vec![
+            FormatTableEntry {
+                format: DrmFourcc::Argb8888 as u32,
+                modifier: DrmModifier::Linear.into(),
+            },
+            FormatTableEntry {
+                format: DrmFourcc::Nv12 as u32,
+                modifier: DrmModifier::I915_y_tiled.into(),
+            },
+            FormatTableEntry {
+                format: u32::MAX,
+                modifier: DrmModifier::Invalid.into(),
+            },
+        ];
+        let feedback = DmabufFeedback {
+            main_device: 0,
+            format_table: format_table.clone(),
+            tranches: vec![],
+        };
+        let bytes = feedback.format_table_bytes();
+        assert_eq!(bytes.len(), format_table.len() * FORMAT_TABLE_ENTRY_SIZE);
+        assert_eq!(FORMAT_TABLE_ENTRY_SIZE, 16);
+        for (entry, bytes) in format_table
+            .iter()
+            .zip(bytes.chunks_exact(FORMAT_TABLE_ENTRY_SIZE))
+        {
+            let (format, rest) = bytes.split_at(4);
+            let (padding, modifier) = rest.split_at(4);
+            assert_eq!(u32::from_ne_bytes(format.try_into().unwrap()), entry.format);
+            assert_eq!(padding, [0; 4]);
+            assert_eq!(
+                u64::from_ne_bytes(modifier.try_into().unwrap()),
+                entry.modifier
+            );
+        }
+    }
+}
@@ -1,132 +1,764 @@
-// pub fn drm_fourcc_to_vk_format(drm_format: drm_fourcc::DrmFourcc) -> Option<vk::Format> {
-//     use drm_fourcc::DrmFourcc as D;
-//     use vk::Format as F;
-//     Some(match drm_format {
-//         D::Abgr1555 | D::Xbgr1555 => F::R5G5B5A1_UNORM_PACK16,
-//         D::Abgr2101010 | D::Xbgr2101010 => F::A2B10G10R10_UNORM_PACK32,
-//         D::Abgr4444 | D::Xbgr4444 => F::A4B4G4R4_UNORM_PACK16,
-//         D::Abgr8888 | D::Xbgr8888 => F::R8G8B8A8_UNORM,
-//         D::Argb1555 | D::Xrgb1555 => F::A1R5G5B5_UNORM_PACK16,
-//         D::Argb2101010 | D::Xrgb2101010 => F::A2R10G10B10_UNORM_PACK32,
-//         D::Argb4444 | D::Xrgb4444 => F::B4G4R4A4_UNORM_PACK16,
-//         D::Argb8888 | D::Xrgb8888 => F::B8G8R8A8_UNORM,
-//         D::Bgr565 => F::B5G6R5_UNORM_PACK16,
-//         D::Bgr888 => F::B8G8R8_UNORM,
-//         D::Bgr888_a8 => F::B8G8R8A8_UNORM,
-//         D::Bgra4444 | D::Bgrx4444 => F::B4G4R4A4_UNORM_PACK16,
-//         D::Bgra5551 | D::Bgrx5551 => F::B5G5R5A1_UNORM_PACK16,
-//         D::Bgra8888 | D::Bgrx8888 => F::B8G8R8A8_UNORM,
-//         D::R16 => F::R16_UNORM,
-//         D::R8 => F::R8_UNORM,
-//         D::Rg1616 => F::R16G16_UNORM,
-//         D::Rg88 => F::R8G8_UNORM,
-//         D::Rgb565 => F::R5G6B5_UNORM_PACK16,
-//         D::Rgb888 => F::R8G8B8_UNORM,
-//         D::Rgb888_a8 => F::R8G8B8A8_UNORM,
-//         D::Rgba4444 | D::Rgbx4444 => F::R4G4B4A4_UNORM_PACK16,
-//         D::Rgba5551 | D::Rgbx5551 => F::R5G5B5A1_UNORM_PACK16,
-//         D::Rgba8888 | D::Rgbx8888 => F::R8G8B8A8_UNORM,
-//         _ => return None,
-//     })
-// }
-
-// pub fn vk_format_to_drm_fourcc(vk_format: vk::Format) -> Option<drm_fourcc::DrmFourcc> {
-//     use drm_fourcc::DrmFourcc as D;
-//     use vk::Format as F;
-//     Some(match vk_format {
-//         F::A2B10G10R10_UNORM_PACK32 | F::A2B10G10R10_SINT_PACK32 => D::Abgr2101010,
-//         F::A2R10G10B10_UNORM_PACK32 | F::A2R10G10B10_SINT_PACK32 => D::Argb2101010,
-//         F::B8G8R8_UNORM | F::B8G8R8_SINT => D::Bgr888,
-//         F::R8G8B8A8_UNORM | F::R8G8B8A8_SINT => D::Rgba8888,
-//         F::R8G8B8_UNORM | F::R8G8B8_SINT => D::Rgb888,
-//         F::B8G8R8A8_UNORM | F::B8G8R8A8_SINT => D::Bgra8888,
-//         F::R16_UNORM | F::R16_SINT => D::R16,
-//         F::R8_UNORM | F::R8_SINT => D::R8,
-//         F::R16G16_UNORM | F::R16G16_SINT => D::Rg1616,
-//         F::R8G8_UNORM | F::R8G8_SINT => D::Rg88,
-
-//         F::A4B4G4R4_UNORM_PACK16 => D::Abgr4444,
-//         F::A1R5G5B5_UNORM_PACK16 => D::Argb1555,
-//         F::B5G6R5_UNORM_PACK16 => D::Bgr565,
-//         F::B4G4R4A4_UNORM_PACK16 => D::Bgra4444,
-//         F::B5G5R5A1_UNORM_PACK16 => D::Bgra5551,
-//         F::R5G6B5_UNORM_PACK16 => D::Rgb565,
-//         F::R4G4B4A4_UNORM_PACK16 => D::Rgba4444,
-//         F::R5G5B5A1_UNORM_PACK16 => D::Rgba5551,
-//         _ => return None,
-//     })
-// }
-
-// pub fn vk_format_to_srgb(vk_format: vk::Format) -> Option<vk::Format> {
-//     use vk::Format as F;
-//     Some(match vk_format {
-//         F::R8_UNORM => F::R8_SRGB,
-//         F::R8G8_UNORM => F::R8G8_SRGB,
-//         F::R8G8B8_UNORM => F::R8G8B8_SRGB,
-//         F::B8G8R8_UNORM => F::B8G8R8_SRGB,
-//         F::R8G8B8A8_UNORM => F::R8G8B8A8_SRGB,
-//         F::B8G8R8A8_UNORM => F::B8G8R8A8_SRGB,
-//         F::A8B8G8R8_UNORM_PACK32 => F::A8B8G8R8_SRGB_PACK32,
-//         F::BC1_RGB_UNORM_BLOCK => F::BC1_RGB_SRGB_BLOCK,
-//         F::BC1_RGBA_UNORM_BLOCK => F::BC1_RGBA_SRGB_BLOCK,
-//         F::BC2_UNORM_BLOCK => F::BC2_SRGB_BLOCK,
-//         F::BC3_UNORM_BLOCK => F::BC3_SRGB_BLOCK,
-//         F::BC7_UNORM_BLOCK => F::BC7_SRGB_BLOCK,
-//         F::ETC2_R8G8B8_UNORM_BLOCK => F::ETC2_R8G8B8_SRGB_BLOCK,
-//         F::ETC2_R8G8B8A1_UNORM_BLOCK => F::ETC2_R8G8B8A1_SRGB_BLOCK,
-//         F::ETC2_R8G8B8A8_UNORM_BLOCK => F::ETC2_R8G8B8A8_SRGB_BLOCK,
-//         _ => return None,
-//     })
-// }
-
-/// Converts a DRM FourCC format directly to a wgpu TextureFormat.
-/// This function combines the conversion logic from drm_fourcc_to_vk_format and vulkan_to_wgpu
-/// to eliminate the need for Vulkan as an intermediate step.
-pub fn fourcc_to_wgpu(drm_format: drm_fourcc::DrmFourcc) -> Option<wgpu::TextureFormat> {
-    use drm_fourcc::DrmFourcc as D;
+use ash::vk;
+use drm_fourcc::DrmFourcc;
+
+/// `fourcc_code` of `drm_fourcc.h`.
+pub const fn fourcc_code(a: u8, b: u8, c: u8, d: u8) -> u32 {
+    a as u32 | (b as u32) << 8 | (c as u32) << 16 | (d as u32) << 24
+}
+
+// fourccs drm-fourcc 2.2 doesn't know
+pub const DRM_FORMAT_ABGR16161616: u32 = fourcc_code(b'A', b'B', b'4', b'8');
+pub const DRM_FORMAT_XBGR16161616: u32 = fourcc_code(b'X', b'B', b'4', b'8');
+pub const DRM_FORMAT_R16F: u32 = fourcc_code(b'R', b' ', b' ', b'H');
+
+/// A DRM FourCC format and the Vulkan and wgpu formats with the same memory layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormatMapping {
+    pub fourcc: u32,
+    pub vk_format: vk::Format,
+    /// [`None`] if wgpu has no equivalent, see [`fourcc_to_wgpu`] for how those are imported
+    pub wgpu_format: Option<wgpu::TextureFormat>,
+    /// sRGB companion of `vk_format`
+    pub vk_srgb: Option<vk::Format>,
+    /// sRGB companion of `wgpu_format`
+    pub wgpu_srgb: Option<wgpu::TextureFormat>,
+}
+
+impl FormatMapping {
+    const fn new(
+        fourcc: u32,
+        vk_format: vk::Format,
+        wgpu_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        Self {
+            fourcc,
+            vk_format,
+            wgpu_format,
+            vk_srgb: None,
+            wgpu_srgb: None,
+        }
+    }
+
+    const fn srgb(self, vk_srgb: vk::Format, wgpu_srgb: Option<wgpu::TextureFormat>) -> Self {
+        Self {
+            vk_srgb: Some(vk_srgb),
+            wgpu_srgb,
+            ..self
+        }
+    }
+}
+
+/// Every DRM FourCC format with a Vulkan equivalent. Formats differing only in an X instead of an
+/// alpha channel share their Vulkan and wgpu formats, the alpha variant comes first and is the
+/// one the inverse mappings return.
+static FORMATS: &[FormatMapping] = {
+    use DrmFourcc as D;
+    use vk::Format as F;
+    use wgpu::TextureFormat as Tf;
+    const fn m(fourcc: D, vk_format: F, wgpu_format: Option<Tf>) -> FormatMapping {
+        FormatMapping::new(fourcc as u32, vk_format, wgpu_format)
+    }
+    &[
+        m(D::R8, F::R8_UNORM, Some(Tf::R8Unorm)).srgb(F::R8_SRGB, None),
+        m(D::R16, F::R16_UNORM, Some(Tf::R16Unorm)),
+        FormatMapping::new(DRM_FORMAT_R16F, F::R16_SFLOAT, Some(Tf::R16Float)),
+        m(D::Gr88, F::R8G8_UNORM, Some(Tf::Rg8Unorm)).srgb(F::R8G8_SRGB, None),
+        m(D::Gr1616, F::R16G16_UNORM, Some(Tf::Rg16Unorm)),
+        m(D::Rgb565, F::R5G6B5_UNORM_PACK16, None),
+        m(D::Bgr565, F::B5G6R5_UNORM_PACK16, None),
+        m(D::Argb1555, F::A1R5G5B5_UNORM_PACK16, None),
+        m(D::Xrgb1555, F::A1R5G5B5_UNORM_PACK16, None),
+        m(D::Abgr1555, F::A1B5G5R5_UNORM_PACK16_KHR, None),
+        m(D::Xbgr1555, F::A1B5G5R5_UNORM_PACK16_KHR, None),
+        m(D::Rgba5551, F::R5G5B5A1_UNORM_PACK16, None),
+        m(D::Rgbx5551, F::R5G5B5A1_UNORM_PACK16, None),
+        m(D::Bgra5551, F::B5G5R5A1_UNORM_PACK16, None),
+        m(D::Bgrx5551, F::B5G5R5A1_UNORM_PACK16, None),
+        m(D::Argb4444, F::A4R4G4B4_UNORM_PACK16, None),
+        m(D::Xrgb4444, F::A4R4G4B4_UNORM_PACK16, None),
+        m(D::Abgr4444, F::A4B4G4R4_UNORM_PACK16, None),
+        m(D::Xbgr4444, F::A4B4G4R4_UNORM_PACK16, None),
+        m(D::Rgba4444, F::R4G4B4A4_UNORM_PACK16, None),
+        m(D::Rgbx4444, F::R4G4B4A4_UNORM_PACK16, None),
+        m(D::Bgra4444, F::B4G4R4A4_UNORM_PACK16, None),
+        m(D::Bgrx4444, F::B4G4R4A4_UNORM_PACK16, None),
+        // the fourcc names the channels from the most significant byte, vulkan from the first byte
+        m(D::Rgb888, F::B8G8R8_UNORM, None).srgb(F::B8G8R8_SRGB, None),
+        m(D::Bgr888, F::R8G8B8_UNORM, None).srgb(F::R8G8B8_SRGB, None),
+        m(D::Abgr8888, F::R8G8B8A8_UNORM, Some(Tf::Rgba8Unorm))
+            .srgb(F::R8G8B8A8_SRGB, Some(Tf::Rgba8UnormSrgb)),
+        m(D::Xbgr8888, F::R8G8B8A8_UNORM, Some(Tf::Rgba8Unorm))
+            .srgb(F::R8G8B8A8_SRGB, Some(Tf::Rgba8UnormSrgb)),
+        m(D::Argb8888, F::B8G8R8A8_UNORM, Some(Tf::Bgra8Unorm))
+            .srgb(F::B8G8R8A8_SRGB, Some(Tf::Bgra8UnormSrgb)),
+        m(D::Xrgb8888, F::B8G8R8A8_UNORM, Some(Tf::Bgra8Unorm))
+            .srgb(F::B8G8R8A8_SRGB, Some(Tf::Bgra8UnormSrgb)),
+        m(
+            D::Abgr2101010,
+            F::A2B10G10R10_UNORM_PACK32,
+            Some(Tf::Rgb10a2Unorm),
+        ),
+        m(
+            D::Xbgr2101010,
+            F::A2B10G10R10_UNORM_PACK32,
+            Some(Tf::Rgb10a2Unorm),
+        ),
+        m(D::Argb2101010, F::A2R10G10B10_UNORM_PACK32, None),
+        m(D::Xrgb2101010, F::A2R10G10B10_UNORM_PACK32, None),
+        m(
+            D::Abgr16161616f,
+            F::R16G16B16A16_SFLOAT,
+            Some(Tf::Rgba16Float),
+        ),
+        m(
+            D::Xbgr16161616f,
+            F::R16G16B16A16_SFLOAT,
+            Some(Tf::Rgba16Float),
+        ),
+        // vulkan has no B16G16R16A16_SFLOAT, see SWIZZLED_FORMATS
+        m(D::Argb16161616f, F::R16G16B16A16_SFLOAT, None),
+        m(D::Xrgb16161616f, F::R16G16B16A16_SFLOAT, None),
+        FormatMapping::new(
+            DRM_FORMAT_ABGR16161616,
+            F::R16G16B16A16_UNORM,
+            Some(Tf::Rgba16Unorm),
+        ),
+        FormatMapping::new(
+            DRM_FORMAT_XBGR16161616,
+            F::R16G16B16A16_UNORM,
+            Some(Tf::Rgba16Unorm),
+        ),
+    ]
+};
+
+/// Formats stored like the wgpu format but with red and blue swapped, they are imported as their
+/// Vulkan format and swizzled while being copied.
+static SWIZZLED_FORMATS: &[(DrmFourcc, wgpu::TextureFormat)] = &[
+    (DrmFourcc::Argb16161616f, wgpu::TextureFormat::Rgba16Float),
+    (DrmFourcc::Xrgb16161616f, wgpu::TextureFormat::Rgba16Float),
+];
+
+/// Formats wgpu has no equivalent for, they are imported as raw integers and unpacked.
+static PACKED_FORMATS: &[(DrmFourcc, PackedFormat)] = {
+    use DrmFourcc as D;
+    // (shift, bits) of r, g, b and a
+    const NONE: (u32, u32) = (0, 0);
+    const fn p16(r: (u32, u32), g: (u32, u32), b: (u32, u32), a: (u32, u32)) -> PackedFormat {
+        PackedFormat::new(2, [r, g, b, a])
+    }
+    const fn p24(r: (u32, u32), g: (u32, u32), b: (u32, u32)) -> PackedFormat {
+        PackedFormat::new(3, [r, g, b, NONE])
+    }
+    const fn p32(r: (u32, u32), g: (u32, u32), b: (u32, u32), a: (u32, u32)) -> PackedFormat {
+        PackedFormat::new(4, [r, g, b, a])
+    }
+    &[
+        (D::Rg88, p16((8, 8), (0, 8), NONE, NONE)),
+        // green first, unlike R16G16_UNORM
+        (D::Rg1616, p32((16, 16), (0, 16), NONE, NONE)),
+        (D::Rgb565, p16((11, 5), (5, 6), (0, 5), NONE)),
+        (D::Bgr565, p16((0, 5), (5, 6), (11, 5), NONE)),
+        (D::Argb1555, p16((10, 5), (5, 5), (0, 5), (15, 1))),
+        (D::Xrgb1555, p16((10, 5), (5, 5), (0, 5), NONE)),
+        (D::Abgr1555, p16((0, 5), (5, 5), (10, 5), (15, 1))),
+        (D::Xbgr1555, p16((0, 5), (5, 5), (10, 5), NONE)),
+        (D::Rgba5551, p16((11, 5), (6, 5), (1, 5), (0, 1))),
+        (D::Rgbx5551, p16((11, 5), (6, 5), (1, 5), NONE)),
+        (D::Bgra5551, p16((1, 5), (6, 5), (11, 5), (0, 1))),
+        (D::Bgrx5551, p16((1, 5), (6, 5), (11, 5), NONE)),
+        (D::Argb4444, p16((8, 4), (4, 4), (0, 4), (12, 4))),
+        (D::Xrgb4444, p16((8, 4), (4, 4), (0, 4), NONE)),
+        (D::Abgr4444, p16((0, 4), (4, 4), (8, 4), (12, 4))),
+        (D::Xbgr4444, p16((0, 4), (4, 4), (8, 4), NONE)),
+        (D::Rgba4444, p16((12, 4), (8, 4), (4, 4), (0, 4))),
+        (D::Rgbx4444, p16((12, 4), (8, 4), (4, 4), NONE)),
+        (D::Bgra4444, p16((4, 4), (8, 4), (12, 4), (0, 4))),
+        (D::Bgrx4444, p16((4, 4), (8, 4), (12, 4), NONE)),
+        (D::Rgb888, p24((16, 8), (8, 8), (0, 8))),
+        (D::Bgr888, p24((0, 8), (8, 8), (16, 8))),
+        (D::Rgba8888, p32((24, 8), (16, 8), (8, 8), (0, 8))),
+        (D::Rgbx8888, p32((24, 8), (16, 8), (8, 8), NONE)),
+        (D::Bgra8888, p32((8, 8), (16, 8), (24, 8), (0, 8))),
+        (D::Bgrx8888, p32((8, 8), (16, 8), (24, 8), NONE)),
+        (D::Argb2101010, p32((20, 10), (10, 10), (0, 10), (30, 2))),
+        (D::Xrgb2101010, p32((20, 10), (10, 10), (0, 10), NONE)),
+    ]
+};
+
+fn mapping(fourcc: u32) -> Option<&'static FormatMapping> {
+    FORMATS.iter().find(|mapping| mapping.fourcc == fourcc)
+}
+
+/// The mapping of a wgpu format, and whether `format` is its sRGB companion.
+fn wgpu_mapping(format: wgpu::TextureFormat) -> Option<(&'static FormatMapping, bool)> {
+    FORMATS.iter().find_map(|mapping| {
+        if mapping.wgpu_format == Some(format) {
+            Some((mapping, false))
+        } else if mapping.wgpu_srgb == Some(format) {
+            Some((mapping, true))
+        } else {
+            None
+        }
+    })
+}
+
+pub fn drm_fourcc_to_vk_format(drm_format: u32) -> Option<vk::Format> {
+    mapping(drm_format).map(|mapping| mapping.vk_format)
+}
+
+/// The DRM FourCC format with the memory layout of `vk_format`, sRGB formats map to the fourcc
+/// of their linear companion.
+pub fn vk_format_to_drm_fourcc(vk_format: vk::Format) -> Option<u32> {
+    FORMATS
+        .iter()
+        .find(|mapping| mapping.vk_format == vk_format || mapping.vk_srgb == Some(vk_format))
+        .map(|mapping| mapping.fourcc)
+}
+
+pub fn vk_format_to_srgb(vk_format: vk::Format) -> Option<vk::Format> {
+    FORMATS
+        .iter()
+        .find(|mapping| mapping.vk_format == vk_format)?
+        .vk_srgb
+}
+
+/// The sRGB companion of a wgpu format as view format, empty if it has none.
+pub fn wgpu_srgb_view_formats(format: wgpu::TextureFormat) -> &'static [wgpu::TextureFormat] {
+    match wgpu_mapping(format) {
+        Some((mapping, false)) => mapping.wgpu_srgb.as_slice(),
+        _ => &[],
+    }
+}
+
+/// The DRM FourCC format with the same memory layout as `format`, the inverse of
+/// [`fourcc_to_wgpu`] for native formats. sRGB formats map to their linear fourcc.
+pub fn wgpu_to_fourcc(format: wgpu::TextureFormat) -> Option<u32> {
+    wgpu_mapping(format).map(|(mapping, _)| mapping.fourcc)
+}
+
+/// The Vulkan format of a wgpu format, keeping sRGB formats sRGB.
+pub fn wgpu_to_vk_format(format: wgpu::TextureFormat) -> Option<vk::Format> {
+    match wgpu_mapping(format)? {
+        (mapping, false) => Some(mapping.vk_format),
+        (mapping, true) => mapping.vk_srgb,
+    }
+}
+
+/// Lists the format properties of `format` and the properties of every DRM format modifier the
+/// physical device supports for it.
+pub fn get_drm_modifiers(
+    instance: &ash::Instance,
+    phys_dev: vk::PhysicalDevice,
+    format: vk::Format,
+) -> (
+    vk::FormatProperties,
+    Vec<vk::DrmFormatModifierPropertiesEXT>,
+) {
+    let mut modifier_list = vk::DrmFormatModifierPropertiesListEXT::default();
+    let mut format_props = vk::FormatProperties2::default().push_next(&mut modifier_list);
+    unsafe { instance.get_physical_device_format_properties2(phys_dev, format, &mut format_props) };
+    let props = format_props.format_properties;
+    let count = modifier_list.drm_format_modifier_count as usize;
+    let mut modifiers = vec![vk::DrmFormatModifierPropertiesEXT::default(); count];
+    let mut modifier_list = vk::DrmFormatModifierPropertiesListEXT::default()
+        .drm_format_modifier_properties(&mut modifiers);
+    let mut format_props = vk::FormatProperties2::default().push_next(&mut modifier_list);
+    unsafe { instance.get_physical_device_format_properties2(phys_dev, format, &mut format_props) };
+    let count = modifier_list.drm_format_modifier_count as usize;
+    modifiers.truncate(count);
+    (props, modifiers)
+}
+
+/// How a DRM FourCC format is turned into a wgpu texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WgpuFormat {
+    /// the dmabuf can be imported as this format directly
+    Native(wgpu::TextureFormat),
+    /// wgpu has no equivalent format, the dmabuf is imported as raw integers and unpacked by the
+    /// conversion shader
+    Packed(PackedFormat),
+    /// the dmabuf is imported as this format with red and blue swapped, and swizzled while being
+    /// copied into a texture of the same format
+    Swizzled(wgpu::TextureFormat),
+}
+
+impl WgpuFormat {
+    /// The format of the texture bevy gets to see.
+    pub fn texture_format(&self) -> wgpu::TextureFormat {
+        match self {
+            WgpuFormat::Native(format) | WgpuFormat::Swizzled(format) => *format,
+            WgpuFormat::Packed(packed) => packed.output_format(),
+        }
+    }
+}
+
+/// A format storing every pixel as a single little endian integer of 16, 24 or 32 bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackedFormat {
+    /// 2, 3 or 4
+    pub bytes_per_pixel: u32,
+    /// bit offset and bit count of the r, g, b and a channel in the pixel, 0 bits for channels
+    /// the format doesn't have
+    pub channels: [(u32, u32); 4],
+}
+
+impl PackedFormat {
+    const fn new(bytes_per_pixel: u32, channels: [(u32, u32); 4]) -> Self {
+        Self {
+            bytes_per_pixel,
+            channels,
+        }
+    }
+
+    /// The format the dmabuf is imported as, 24 bit pixels are split into three 8 bit texels
+    /// since there are no 24 bit formats.
+    pub fn raw_format(&self) -> (wgpu::TextureFormat, vk::Format) {
+        match self.bytes_per_pixel {
+            2 => (wgpu::TextureFormat::R16Uint, vk::Format::R16_UINT),
+            4 => (wgpu::TextureFormat::R32Uint, vk::Format::R32_UINT),
+            _ => (wgpu::TextureFormat::R8Uint, vk::Format::R8_UINT),
+        }
+    }
+
+    /// Number of raw texels per pixel.
+    pub fn texels_per_pixel(&self) -> u32 {
+        match self.bytes_per_pixel {
+            3 => 3,
+            _ => 1,
+        }
+    }
+
+    /// The format the pixels are unpacked into, channels with more than 8 bits keep their
+    /// precision.
+    pub fn output_format(&self) -> wgpu::TextureFormat {
+        if self.channels.iter().any(|(_, bits)| *bits > 8) {
+            wgpu::TextureFormat::Rgba16Float
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        }
+    }
+}
+
+/// Maps a DRM FourCC format to the wgpu format it's imported as, distinguishing formats wgpu can
+/// sample directly from ones that have to be unpacked.
+pub fn fourcc_to_wgpu(drm_format: u32) -> Option<WgpuFormat> {
+    if let Some(format) = mapping(drm_format).and_then(|mapping| mapping.wgpu_format) {
+        return Some(WgpuFormat::Native(format));
+    }
+    if let Some((_, format)) = SWIZZLED_FORMATS
+        .iter()
+        .find(|(fourcc, _)| *fourcc as u32 == drm_format)
+    {
+        return Some(WgpuFormat::Swizzled(*format));
+    }
+    PACKED_FORMATS
+        .iter()
+        .find(|(fourcc, _)| *fourcc as u32 == drm_format)
+        .map(|(_, packed)| WgpuFormat::Packed(*packed))
+}
+
+/// Whether the format has an alpha channel, the X channel of formats like `Xrgb8888` is undefined
+/// and must not be used as alpha.
+pub fn fourcc_has_alpha(drm_format: u32) -> bool {
+    use DrmFourcc as D;
+    if drm_format == DRM_FORMAT_ABGR16161616 {
+        return true;
+    }
+    DrmFourcc::try_from(drm_format).is_ok_and(|drm_format| {
+        matches!(
+            drm_format,
+            D::Argb8888
+                | D::Abgr8888
+                | D::Rgba8888
+                | D::Bgra8888
+                | D::Argb2101010
+                | D::Abgr2101010
+                | D::Argb1555
+                | D::Abgr1555
+                | D::Rgba5551
+                | D::Bgra5551
+                | D::Argb4444
+                | D::Abgr4444
+                | D::Rgba4444
+                | D::Bgra4444
+                | D::Abgr16161616f
+                | D::Argb16161616f
+        )
+    })
+}
+
+/// Memory layout of a DRM FourCC format. Every format is little endian, `DRM_FORMAT_BIG_ENDIAN`
+/// fourccs aren't supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrmFormatInfo {
+    /// bytes per pixel of every plane, counting the pixels of the subsampled plane
+    pub bytes_per_pixel: &'static [u32],
+    /// horizontal and vertical divisor of the size of every plane but the first
+    pub subsampling: (u32, u32),
+    pub has_alpha: bool,
+    pub yuv: bool,
+}
+
+impl DrmFormatInfo {
+    pub fn plane_count(&self) -> usize {
+        self.bytes_per_pixel.len()
+    }
+
+    /// Width and height of `plane` in pixels for a `width` x `height` image.
+    pub fn plane_size(&self, plane: usize, width: u32, height: u32) -> (u32, u32) {
+        match plane {
+            0 => (width, height),
+            _ => (
+                width.div_ceil(self.subsampling.0),
+                height.div_ceil(self.subsampling.1),
+            ),
+        }
+    }
+
+    /// The smallest stride `plane` can have for an image `width` pixels wide.
+    pub fn min_stride(&self, plane: usize, width: u32) -> u64 {
+        let (plane_width, _) = self.plane_size(plane, width, 1);
+        u64::from(plane_width) * u64::from(self.bytes_per_pixel[plane])
+    }
+}
+
+/// The memory layout of every format this crate can import, [`None`] for other formats.
+pub fn format_info(drm_format: u32) -> Option<DrmFormatInfo> {
+    use DrmFourcc as D;
+    let rgb = |bytes_per_pixel: &'static [u32]| DrmFormatInfo {
+        bytes_per_pixel,
+        subsampling: (1, 1),
+        has_alpha: fourcc_has_alpha(drm_format),
+        yuv: false,
+    };
+    let yuv = |bytes_per_pixel: &'static [u32], subsampling: (u32, u32)| DrmFormatInfo {
+        bytes_per_pixel,
+        subsampling,
+        has_alpha: false,
+        yuv: true,
+    };
+    match drm_format {
+        DRM_FORMAT_R16F => return Some(rgb(&[2])),
+        DRM_FORMAT_ABGR16161616 | DRM_FORMAT_XBGR16161616 => return Some(rgb(&[8])),
+        _ => {}
+    }
+    Some(match DrmFourcc::try_from(drm_format).ok()? {
+        D::R8 => rgb(&[1]),
+        D::R16
+        | D::Gr88
+        | D::Rg88
+        | D::Rgb565
+        | D::Bgr565
+        | D::Argb1555
+        | D::Xrgb1555
+        | D::Abgr1555
+        | D::Xbgr1555
+        | D::Rgba5551
+        | D::Rgbx5551
+        | D::Bgra5551
+        | D::Bgrx5551
+        | D::Argb4444
+        | D::Xrgb4444
+        | D::Abgr4444
+        | D::Xbgr4444
+        | D::Rgba4444
+        | D::Rgbx4444
+        | D::Bgra4444
+        | D::Bgrx4444 => rgb(&[2]),
+        D::Rgb888 | D::Bgr888 => rgb(&[3]),
+        D::Gr1616
+        | D::Rg1616
+        | D::Abgr8888
+        | D::Xbgr8888
+        | D::Argb8888
+        | D::Xrgb8888
+        | D::Rgba8888
+        | D::Rgbx8888
+        | D::Bgra8888
+        | D::Bgrx8888
+        | D::Abgr2101010
+        | D::Xbgr2101010
+        | D::Argb2101010
+        | D::Xrgb2101010 => rgb(&[4]),
+        D::Abgr16161616f | D::Xbgr16161616f | D::Argb16161616f | D::Xrgb16161616f => rgb(&[8]),
+        D::Nv12 | D::Nv21 => yuv(&[1, 2], (2, 2)),
+        D::P010 => yuv(&[2, 4], (2, 2)),
+        D::Yuv420 => yuv(&[1, 1, 1], (2, 2)),
+        // a 4 byte macropixel holds two pixels
+        D::Yuyv => yuv(&[2], (2, 1)),
+        _ => return None,
+    })
+}
+
+/// Every DRM FourCC format [`fourcc_to_wgpu`] or [`fourcc_to_yuv`] has a mapping for.
+pub fn wgpu_fourccs() -> impl Iterator<Item = u32> {
+    FORMATS
+        .iter()
+        .filter(|mapping| mapping.wgpu_format.is_some())
+        .map(|mapping| mapping.fourcc)
+        .chain(SWIZZLED_FORMATS.iter().map(|(fourcc, _)| *fourcc as u32))
+        .chain(PACKED_FORMATS.iter().map(|(fourcc, _)| *fourcc as u32))
+        .chain(YUV_FORMATS.iter().map(|fourcc| *fourcc as u32))
+}
+
+/// Every format [`fourcc_to_yuv`] describes.
+static YUV_FORMATS: &[DrmFourcc] = &[
+    DrmFourcc::Nv12,
+    DrmFourcc::Nv21,
+    DrmFourcc::P010,
+    DrmFourcc::Yuv420,
+    DrmFourcc::Yuyv,
+];
+
+/// How the planes of a yuv format are laid out, selects the sampling code of the conversion shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YuvLayout {
+    /// Y plane followed by an interleaved CbCr plane
+    Nv12,
+    /// Y plane followed by an interleaved CrCb plane
+    Nv21,
+    /// separate Y, Cb and Cr planes
+    Yuv420,
+    /// single plane of Y0 Cb Y1 Cr macropixels
+    Yuyv,
+}
+
+/// A single plane of a yuv format, imported as its own texture.
+#[derive(Clone, Copy, Debug)]
+pub struct YuvPlane {
+    pub wgpu_format: wgpu::TextureFormat,
+    pub vk_format: vk::Format,
+    /// horizontal and vertical divisor of the plane size relative to the image size
+    pub subsampling: (u32, u32),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct YuvFormat {
+    pub layout: YuvLayout,
+    pub planes: &'static [YuvPlane],
+    /// bits per component that are actually used by the format
+    pub bit_depth: u32,
+    /// format of the rgb texture the planes get converted into
+    pub output_format: wgpu::TextureFormat,
+}
+
+const fn yuv_plane(
+    wgpu_format: wgpu::TextureFormat,
+    vk_format: vk::Format,
+    subsampling: (u32, u32),
+) -> YuvPlane {
+    YuvPlane {
+        wgpu_format,
+        vk_format,
+        subsampling,
+    }
+}
+
+/// Describes how a multi-planar or packed yuv DRM FourCC format is imported plane by plane,
+/// returns [`None`] for non yuv formats.
+pub fn fourcc_to_yuv(drm_format: u32) -> Option<YuvFormat> {
+    use DrmFourcc as D;
+    use vk::Format as F;
     use wgpu::TextureFormat as Tf;
 
-    Some(match drm_format {
-        // Basic single-channel formats
-        D::R8 => Tf::R8Unorm,
-        D::R16 => Tf::R16Unorm,
-
-        // Dual-channel formats
-        D::Rg88 => Tf::Rg8Unorm,
-        D::Rg1616 => Tf::Rg16Unorm,
-
-        // Packed 16-bit formats (map to closest available formats)
-        D::Abgr1555 | D::Xbgr1555 => Tf::Rgba8Unorm, // Promote to 32-bit
-        D::Argb1555 | D::Xrgb1555 => Tf::Bgra8Unorm, // Promote to 32-bit
-        D::Abgr4444 | D::Xbgr4444 => Tf::Rgba8Unorm, // Promote to 32-bit
-        D::Argb4444 | D::Xrgb4444 => Tf::Bgra8Unorm, // Promote to 32-bit
-        D::Bgra4444 | D::Bgrx4444 => Tf::Bgra8Unorm, // Promote to 32-bit
-        D::Bgra5551 | D::Bgrx5551 => Tf::Bgra8Unorm, // Promote to 32-bit
-        D::Rgba4444 | D::Rgbx4444 => Tf::Rgba8Unorm, // Promote to 32-bit
-        D::Rgba5551 | D::Rgbx5551 => Tf::Rgba8Unorm, // Promote to 32-bit
-
-        // 16-bit RGB565 formats (promote to 32-bit since wgpu lacks these)
-        D::Bgr565 => Tf::Bgra8Unorm, // Add alpha channel
-        D::Rgb565 => Tf::Rgba8Unorm, // Add alpha channel
-
-        // 24-bit formats (promote to 32-bit since wgpu doesn't support 24-bit)
-        D::Rgb888 => Tf::Rgba8Unorm, // Add alpha channel
-        D::Bgr888 => Tf::Bgra8Unorm, // Add alpha channel
-
-        // 32-bit formats - main target formats
-        D::Rgba8888 | D::Rgbx8888 => Tf::Rgba8Unorm,
-        D::Bgra8888 | D::Bgrx8888 => Tf::Bgra8Unorm,
-        D::Argb8888 | D::Xrgb8888 => Tf::Bgra8Unorm, // ARGB maps to BGRA
-        D::Abgr8888 | D::Xbgr8888 => Tf::Rgba8Unorm, // ABGR maps to RGBA
-
-        // Special 32-bit formats
-        D::Rgb888_a8 => Tf::Rgba8Unorm,
-        D::Bgr888_a8 => Tf::Bgra8Unorm,
-
-        // 10-bit formats
-        D::Argb2101010 | D::Xrgb2101010 => Tf::Rgb10a2Unorm,
-        D::Abgr2101010 | D::Xbgr2101010 => Tf::Rgb10a2Unorm,
+    const NV12: &[YuvPlane] = &[
+        yuv_plane(Tf::R8Unorm, F::R8_UNORM, (1, 1)),
+        yuv_plane(Tf::Rg8Unorm, F::R8G8_UNORM, (2, 2)),
+    ];
+    const P010: &[YuvPlane] = &[
+        yuv_plane(Tf::R16Unorm, F::R16_UNORM, (1, 1)),
+        yuv_plane(Tf::Rg16Unorm, F::R16G16_UNORM, (2, 2)),
+    ];
+    const YUV420: &[YuvPlane] = &[
+        yuv_plane(Tf::R8Unorm, F::R8_UNORM, (1, 1)),
+        yuv_plane(Tf::R8Unorm, F::R8_UNORM, (2, 2)),
+        yuv_plane(Tf::R8Unorm, F::R8_UNORM, (2, 2)),
+    ];
+    // every texel holds two pixels
+    const YUYV: &[YuvPlane] = &[yuv_plane(Tf::Rgba8Unorm, F::R8G8B8A8_UNORM, (2, 1))];
 
+    Some(match DrmFourcc::try_from(drm_format).ok()? {
+        D::Nv12 => YuvFormat {
+            layout: YuvLayout::Nv12,
+            planes: NV12,
+            bit_depth: 8,
+            output_format: Tf::Rgba8Unorm,
+        },
+        D::Nv21 => YuvFormat {
+            layout: YuvLayout::Nv21,
+            planes: NV12,
+            bit_depth: 8,
+            output_format: Tf::Rgba8Unorm,
+        },
+        D::P010 => YuvFormat {
+            layout: YuvLayout::Nv12,
+            planes: P010,
+            bit_depth: 10,
+            output_format: Tf::Rgba16Float,
+        },
+        D::Yuv420 => YuvFormat {
+            layout: YuvLayout::Yuv420,
+            planes: YUV420,
+            bit_depth: 8,
+            output_format: Tf::Rgba8Unorm,
+        },
+        D::Yuyv => YuvFormat {
+            layout: YuvLayout::Yuyv,
+            planes: YUYV,
+            bit_depth: 8,
+            output_format: Tf::Rgba8Unorm,
+        },
         _ => return None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DrmFourcc as D;
+
+    #[test]
+    fn vk_round_trip() {
+        for mapping in FORMATS {
+            assert_eq!(
+                drm_fourcc_to_vk_format(mapping.fourcc),
+                Some(mapping.vk_format)
+            );
+            // x variants map back to their alpha variant, which has the same layout
+            let fourcc = vk_format_to_drm_fourcc(mapping.vk_format).unwrap();
+            assert_eq!(drm_fourcc_to_vk_format(fourcc), Some(mapping.vk_format));
+            assert_eq!(vk_format_to_srgb(mapping.vk_format), mapping.vk_srgb);
+            if let Some(srgb) = mapping.vk_srgb {
+                assert_eq!(vk_format_to_drm_fourcc(srgb), Some(fourcc));
+            }
+        }
+    }
+
+    #[test]
+    fn wgpu_round_trip() {
+        for mapping in FORMATS {
+            let Some(format) = mapping.wgpu_format else {
+                assert!(mapping.wgpu_srgb.is_none());
+                continue;
+            };
+            assert_eq!(
+                fourcc_to_wgpu(mapping.fourcc),
+                Some(WgpuFormat::Native(format))
+            );
+            let fourcc = wgpu_to_fourcc(format).unwrap();
+            assert_eq!(fourcc_to_wgpu(fourcc), Some(WgpuFormat::Native(format)));
+            assert_eq!(drm_fourcc_to_vk_format(fourcc), Some(mapping.vk_format));
+            assert_eq!(wgpu_to_vk_format(format), Some(mapping.vk_format));
+            if let Some(srgb) = mapping.wgpu_srgb {
+                assert_eq!(srgb.remove_srgb_suffix(), format);
+                assert_eq!(wgpu_to_fourcc(srgb), Some(fourcc));
+                assert_eq!(wgpu_to_vk_format(srgb), mapping.vk_srgb);
+                assert_eq!(wgpu_srgb_view_formats(format), &[srgb]);
+            }
+        }
+    }
+
+    #[test]
+    fn formats_are_unique() {
+        for (i, mapping) in FORMATS.iter().enumerate() {
+            assert!(
+                FORMATS[i + 1..]
+                    .iter()
+                    .all(|other| other.fourcc != mapping.fourcc)
+            );
+        }
+        for (i, (fourcc, _)) in PACKED_FORMATS.iter().enumerate() {
+            assert!(
+                PACKED_FORMATS[i + 1..]
+                    .iter()
+                    .all(|(other, _)| other != fourcc)
+            );
+            // native formats take precedence, a packed entry would never be used
+            assert!(mapping(*fourcc as u32).is_none_or(|mapping| mapping.wgpu_format.is_none()));
+        }
+    }
+
+    #[test]
+    fn format_info_matches_mappings() {
+        for fourcc in wgpu_fourccs().filter(|fourcc| fourcc_to_yuv(*fourcc).is_none()) {
+            let info = format_info(fourcc).unwrap();
+            assert_eq!(info.plane_count(), 1, "{fourcc:#x}");
+            assert!(!info.yuv);
+            match fourcc_to_wgpu(fourcc).unwrap() {
+                WgpuFormat::Native(format) | WgpuFormat::Swizzled(format) => {
+                    assert_eq!(format.block_copy_size(None), Some(info.bytes_per_pixel[0]));
+                }
+                WgpuFormat::Packed(packed) => {
+                    assert_eq!(
+                        info.bytes_per_pixel,
+                        &[packed.bytes_per_pixel],
+                        "{fourcc:#x}"
+                    );
+                }
+            }
+        }
+        for fourcc in YUV_FORMATS {
+            let fourcc = *fourcc as u32;
+            assert!(wgpu_fourccs().any(|other| other == fourcc));
+            let info = format_info(fourcc).unwrap();
+            assert_eq!(
+                info.plane_count(),
+                fourcc_to_yuv(fourcc).unwrap().planes.len()
+            );
+            assert!(info.yuv);
+        }
+    }
+
+    #[test]
+    fn packed_channels_fit() {
+        for (fourcc, packed) in PACKED_FORMATS {
+            let mut used = 0u32;
+            for (shift, bits) in packed.channels.into_iter().filter(|(_, bits)| *bits > 0) {
+                assert!(shift + bits <= packed.bytes_per_pixel * 8, "{fourcc}");
+                let mask = (((1u64 << bits) - 1) as u32) << shift;
+                assert_eq!(used & mask, 0, "{fourcc}");
+                used |= mask;
+            }
+            assert_eq!(
+                packed.channels[3].1 > 0,
+                fourcc_has_alpha(*fourcc as u32),
+                "{fourcc}"
+            );
+        }
+    }
+
+    #[test]
+    fn fourccs_missing_from_drm_fourcc() {
+        assert_eq!(DRM_FORMAT_ABGR16161616, 0x3834_4241);
+        assert_eq!(DRM_FORMAT_XBGR16161616, 0x3834_4258);
+        assert_eq!(DRM_FORMAT_R16F, 0x4820_2052);
+        for (fourcc, format) in [
+            (DRM_FORMAT_ABGR16161616, wgpu::TextureFormat::Rgba16Unorm),
+            (DRM_FORMAT_R16F, wgpu::TextureFormat::R16Float),
+        ] {
+            assert!(DrmFourcc::try_from(fourcc).is_err());
+            assert_eq!(fourcc_to_wgpu(fourcc), Some(WgpuFormat::Native(format)));
+            assert_eq!(wgpu_to_fourcc(format), Some(fourcc));
+        }
+        assert_eq!(
+            fourcc_to_wgpu(DRM_FORMAT_XBGR16161616),
+            Some(WgpuFormat::Native(wgpu::TextureFormat::Rgba16Unorm))
+        );
+        assert!(fourcc_has_alpha(DRM_FORMAT_ABGR16161616));
+        assert!(!fourcc_has_alpha(DRM_FORMAT_XBGR16161616));
+        assert!(!fourcc_has_alpha(DRM_FORMAT_R16F));
+    }
+
+    #[test]
+    fn swizzled_formats() {
+        for (fourcc, format) in SWIZZLED_FORMATS {
+            let fourcc = *fourcc as u32;
+            assert_eq!(fourcc_to_wgpu(fourcc), Some(WgpuFormat::Swizzled(*format)));
+            // imported as the vulkan format of the format they are swizzled into
+            assert_eq!(drm_fourcc_to_vk_format(fourcc), wgpu_to_vk_format(*format));
+            assert_ne!(wgpu_to_fourcc(*format), Some(fourcc));
+        }
+        assert!(fourcc_has_alpha(D::Argb16161616f as u32));
+        assert!(!fourcc_has_alpha(D::Xrgb16161616f as u32));
+    }
+}
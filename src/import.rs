@@ -1,17 +1,25 @@
 #![warn(clippy::unwrap_used, clippy::expect_used)]
 use std::{
     fmt::Debug,
-    sync::{Arc, Mutex},
+    os::fd::{AsFd as _, AsRawFd as _, BorrowedFd, IntoRawFd as _, OwnedFd},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+    time::Duration,
 };
 
+use ash::vk;
+
 use bevy::{
-    app::Plugin,
+    app::{First, Plugin},
     asset::{Assets, Handle, RenderAssetUsages},
     ecs::{
+        event::{Event, EventWriter},
         resource::Resource,
-        schedule::{IntoScheduleConfigs as _, SystemSet},
+        schedule::{IntoScheduleConfigs as _, SystemSet, common_conditions::resource_exists},
         system::{Res, ResMut},
-        world::World,
     },
     image::Image,
     pbr::{PreparedMaterial, StandardMaterial},
@@ -21,7 +29,7 @@ use bevy::{
         extract_resource::{ExtractResource, ExtractResourcePlugin},
         render_asset::{RenderAssets, prepare_assets},
         render_resource::{Texture, TextureView},
-        renderer::RenderDevice,
+        renderer::{RenderDevice, RenderQueue},
         texture::GpuImage,
     },
     utils::default,
@@ -31,18 +39,59 @@ use thiserror::Error;
 use tracing::{debug, debug_span, error, warn};
 use wgpu::{
     TextureUsages, TextureViewDescriptor,
-    hal::{Device, MemoryFlags, TextureDescriptor, TextureUses, vulkan::Api as Vulkan},
+    hal::{MemoryFlags, TextureDescriptor, TextureUses, vulkan::Api as Vulkan},
 };
 
-use crate::{dmatex::Dmatex, format_mapping::fourcc_to_wgpu};
+use crate::{
+    capabilities::{DmabufCapabilities, DrmDevice},
+    convert::{ConvertPipelines, ConvertSource},
+    dmatex::{
+        ColorMatrix, ColorRange, DmabufId, Dmatex, DmatexExplicitSync, DmatexPlane,
+        DmatexTransform, DmatexValidationError,
+    },
+    format_mapping::{
+        DrmFormatInfo, PackedFormat, WgpuFormat, YuvFormat, drm_fourcc_to_vk_format, format_info,
+        fourcc_has_alpha, fourcc_to_wgpu, fourcc_to_yuv, get_drm_modifiers, vk_format_to_srgb,
+        wgpu_srgb_view_formats,
+    },
+    modifier::Modifier,
+    stream::{DmabufStream, DmabufStreamMode, StreamFrame, StreamState},
+    sync::{ContentsBackup, ExplicitSync, ImplicitSync, OwnershipTransfer, OwnershipTransfers},
+};
 
-pub struct DmabufImportPlugin;
+pub struct DmabufImportPlugin {
+    /// Bridge the implicit sync of imported dmabufs that don't use explicit sync, for producers
+    /// that rely on the kernel tracking their rendering. Rendering waits for their pending writes,
+    /// but bevy's reads aren't attached to the dmabufs, producers still have to wait for the
+    /// [`DmatexReleased`] event (or drop callback) before writing to them again.
+    pub implicit_sync: bool,
+    /// How many no longer used imports are kept around, so dmabufs set again (like those of a
+    /// producer cycling through a swapchain) don't have to be imported again. 0 disables caching.
+    pub import_cache_size: usize,
+}
+
+impl Default for DmabufImportPlugin {
+    fn default() -> Self {
+        Self {
+            implicit_sync: false,
+            import_cache_size: 8,
+        }
+    }
+}
 
 impl Plugin for DmabufImportPlugin {
     fn build(&self, app: &mut bevy::app::App) {
-        let handles = ImportedDmatexs(default());
+        let (released, receiver) = mpsc::channel();
+        let handles = ImportedDmatexs {
+            images: default(),
+            next_id: default(),
+            released,
+        };
         app.insert_resource(handles.clone());
         app.add_plugins(ExtractResourcePlugin::<ImportedDmatexs>::default());
+        app.add_event::<DmatexReleased>();
+        app.insert_resource(ReleasedDmatexs(Mutex::new(receiver)));
+        app.add_systems(First, send_dmatex_released_events);
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app.configure_sets(
                 Render,
@@ -54,45 +103,143 @@ impl Plugin for DmabufImportPlugin {
                     DmatexRenderSystemSet::AcquireDmatexs
                         .in_set(RenderSet::PrepareAssets)
                         .after(DmatexRenderSystemSet::InsertIntoGpuImages),
+                    DmatexRenderSystemSet::ConvertDmatexs
+                        .in_set(RenderSet::PrepareAssets)
+                        .after(DmatexRenderSystemSet::AcquireDmatexs),
                     DmatexRenderSystemSet::ReleaseDmatexs.in_set(RenderSet::Cleanup),
                 ),
             );
             render_app.add_systems(
                 Render,
-                insert_dmatex_into_gpu_images.in_set(DmatexRenderSystemSet::InsertIntoGpuImages),
+                (
+                    insert_dmatex_into_gpu_images
+                        .in_set(DmatexRenderSystemSet::InsertIntoGpuImages),
+                    acquire_dmatexs
+                        .run_if(resource_exists::<OwnershipTransfers>)
+                        .in_set(DmatexRenderSystemSet::AcquireDmatexs),
+                    convert_dmatexs.in_set(DmatexRenderSystemSet::ConvertDmatexs),
+                    release_dmatexs.in_set(DmatexRenderSystemSet::ReleaseDmatexs),
+                    transfer_dmatexs_to_foreign_queue
+                        .run_if(resource_exists::<OwnershipTransfers>)
+                        .in_set(DmatexRenderSystemSet::ReleaseDmatexs),
+                ),
             );
-            // render_app.add_systems(
-            //     Render,
-            //     (
-            //         acquire_dmatex_images.in_set(DmatexRenderSystemSet::AcquireDmatexs),
-            //         release_dmatex_images.in_set(DmatexRenderSystemSet::ReleaseDmatexs),
-            //     ),
-            // );
+            render_app.init_resource::<DmatexReleases>();
+            render_app.insert_resource(ImportCache::new(self.import_cache_size));
         } else {
             warn!("unable to init dmabuf importing!");
         }
     }
+
+    fn finish(&self, app: &mut bevy::app::App) {
+        if let Some(device) = app.world().get_resource::<RenderDevice>() {
+            match DmabufCapabilities::query(device) {
+                Ok(capabilities) => {
+                    app.insert_resource(capabilities);
+                }
+                Err(err) => warn!("unable to query dmabuf import capabilities: {err}"),
+            }
+        }
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<ConvertPipelines>();
+            let device = render_app.world().resource::<RenderDevice>();
+            let transfers = unsafe {
+                device
+                    .wgpu_device()
+                    .as_hal::<Vulkan, _, _>(|dev| dev.map(OwnershipTransfers::new))
+            };
+            if let Some(transfers) = transfers {
+                render_app.insert_resource(transfers);
+            }
+            if self.implicit_sync {
+                render_app.init_resource::<ImplicitSync>();
+            }
+        }
+    }
 }
 
 #[derive(SystemSet, Hash, Debug, Clone, PartialEq, Eq, Copy)]
 pub enum DmatexRenderSystemSet {
     InsertIntoGpuImages,
+    /// waits for the acquire points of explicitly synced dmatexs, or the pending writes of
+    /// implicitly synced ones
     AcquireDmatexs,
+    /// converts dmatexs that can't be sampled directly (like yuv formats) into rgb textures
+    ConvertDmatexs,
+    /// signals the release points of explicitly synced dmatexs that are no longer used
     ReleaseDmatexs,
 }
 
 #[derive(Resource, Clone, ExtractResource)]
-pub struct ImportedDmatexs(Arc<Mutex<HashMap<Handle<Image>, DmaImage>>>);
+pub struct ImportedDmatexs {
+    images: Arc<Mutex<HashMap<Handle<Image>, DmaImage>>>,
+    next_id: Arc<AtomicU64>,
+    released: mpsc::Sender<DmatexReleased>,
+}
 
 #[derive(Debug)]
 enum DmaImage {
-    UnImported(Dmatex, DropCallback, DmatexUsage),
+    UnImported(Dmatex, DropCallback, DmatexUsage, DmatexRelease),
     Imported(ImportedTexture),
+    Stream(StreamState),
 }
 
-#[derive(Clone, Copy, Debug)]
-pub enum DmatexUsage {
-    Sampling,
+impl DmaImage {
+    /// The import currently shown for this image.
+    fn imported(&self) -> Option<&ImportedTexture> {
+        match self {
+            DmaImage::UnImported(_, _, _, _) => None,
+            DmaImage::Imported(tex) => Some(tex),
+            DmaImage::Stream(stream) => stream.current.as_ref(),
+        }
+    }
+    fn imported_mut(&mut self) -> Option<&mut ImportedTexture> {
+        match self {
+            DmaImage::UnImported(_, _, _, _) => None,
+            DmaImage::Imported(tex) => Some(tex),
+            DmaImage::Stream(stream) => stream.current.as_mut(),
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// How bevy uses an imported dmatex. Writing usages need the dmatex to be imported directly,
+    /// dmatexs that are converted (like yuv or transformed ones) can only be read.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct DmatexUsage: u32 {
+        const SAMPLING = 1 << 0;
+        const RENDER_TARGET = 1 << 1;
+        const STORAGE = 1 << 2;
+        const COPY_SOURCE = 1 << 3;
+        const COPY_DESTINATION = 1 << 4;
+    }
+}
+
+impl DmatexUsage {
+    /// usages that write to the dmabuf
+    const WRITES: Self = Self::RENDER_TARGET
+        .union(Self::STORAGE)
+        .union(Self::COPY_DESTINATION);
+
+    pub fn texture_usages(self) -> TextureUsages {
+        let mut usages = TextureUsages::empty();
+        if self.contains(Self::SAMPLING) {
+            usages |= TextureUsages::TEXTURE_BINDING;
+        }
+        if self.contains(Self::RENDER_TARGET) {
+            usages |= TextureUsages::RENDER_ATTACHMENT;
+        }
+        if self.contains(Self::STORAGE) {
+            usages |= TextureUsages::STORAGE_BINDING;
+        }
+        if self.contains(Self::COPY_SOURCE) {
+            usages |= TextureUsages::COPY_SRC;
+        }
+        if self.contains(Self::COPY_DESTINATION) {
+            usages |= TextureUsages::COPY_DST;
+        }
+        usages
+    }
 }
 
 pub struct DropCallback(pub Option<Box<dyn FnOnce() + 'static + Send + Sync>>);
@@ -109,6 +256,42 @@ impl Drop for DropCallback {
     }
 }
 
+/// Identifies a dmatex passed to [`ImportedDmatexs::set_with_id`] or [`ImportedDmatexs::submit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DmatexId(pub u64);
+
+/// Sent once a dmatex is no longer used and the GPU work reading it has completed, the producer
+/// can write to its dmabufs again.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DmatexReleased {
+    pub id: DmatexId,
+}
+
+/// Sends [`DmatexReleased`] when dropped.
+#[derive(Debug)]
+pub(crate) struct DmatexRelease {
+    id: DmatexId,
+    released: mpsc::Sender<DmatexReleased>,
+}
+
+impl Drop for DmatexRelease {
+    fn drop(&mut self) {
+        // fails if the app is shutting down, then nobody is listening anymore
+        _ = self.released.send(DmatexReleased { id: self.id });
+    }
+}
+
+#[derive(Resource)]
+struct ReleasedDmatexs(Mutex<mpsc::Receiver<DmatexReleased>>);
+
+fn send_dmatex_released_events(
+    released: Res<ReleasedDmatexs>,
+    mut events: EventWriter<DmatexReleased>,
+) {
+    #[expect(clippy::unwrap_used)]
+    events.write_batch(released.0.lock().unwrap().try_iter());
+}
+
 impl ImportedDmatexs {
     pub fn set(
         &self,
@@ -117,13 +300,110 @@ impl ImportedDmatexs {
         usage: DmatexUsage,
         on_drop: Option<Box<dyn FnOnce() + 'static + Send + Sync>>,
     ) -> Result<Handle<Image>, ImportError> {
-        let handle = get_handle(images, &buf)?;
+        self.set_with_id(images, buf, usage, on_drop)
+            .map(|(handle, _)| handle)
+    }
+    /// Like [`ImportedDmatexs::set`], but also returns the id of `buf`, a [`DmatexReleased`]
+    /// event is sent once the image is dropped and no longer rendered.
+    pub fn set_with_id(
+        &self,
+        images: &mut Assets<Image>,
+        buf: Dmatex,
+        usage: DmatexUsage,
+        on_drop: Option<Box<dyn FnOnce() + 'static + Send + Sync>>,
+    ) -> Result<(Handle<Image>, DmatexId), ImportError> {
+        buf.validate()?;
+        let handle = get_handle(images, &buf, usage)?;
+        let release = self.new_release();
+        let id = release.id;
         #[expect(clippy::unwrap_used)]
-        self.0.lock().unwrap().insert(
+        self.images.lock().unwrap().insert(
             handle.clone_weak(),
-            DmaImage::UnImported(buf, DropCallback(on_drop), usage),
+            DmaImage::UnImported(buf, DropCallback(on_drop), usage, release),
         );
-        Ok(handle)
+        Ok((handle, id))
+    }
+    fn new_release(&self) -> DmatexRelease {
+        DmatexRelease {
+            id: DmatexId(self.next_id.fetch_add(1, Ordering::Relaxed)),
+            released: self.released.clone(),
+        }
+    }
+    /// Whether the alpha channel of the dmatex behind `handle` is meaningful, if it isn't,
+    /// materials showing it can use `AlphaMode::Opaque`.
+    pub fn has_alpha(&self, handle: &Handle<Image>) -> Option<bool> {
+        #[expect(clippy::unwrap_used)]
+        let imported = self.images.lock().unwrap();
+        match imported.get(handle)? {
+            DmaImage::UnImported(buf, _, _, _) => Some(fourcc_has_alpha(buf.format)),
+            DmaImage::Imported(tex) => Some(tex.has_alpha),
+            DmaImage::Stream(stream) => stream.has_alpha(),
+        }
+    }
+    /// Creates an image that shows the frames submitted to it with [`Self::submit`].
+    pub fn create_stream(
+        &self,
+        images: &mut Assets<Image>,
+        mode: DmabufStreamMode,
+    ) -> DmabufStream {
+        // kept in the main world too, so sprites and UI know the size of the current frame
+        let handle = images.add(Image::new_uninit(
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            wgpu::TextureDimension::D2,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        ));
+        #[expect(clippy::unwrap_used)]
+        self.images.lock().unwrap().insert(
+            handle.clone_weak(),
+            DmaImage::Stream(StreamState::new(mode)),
+        );
+        DmabufStream { handle, mode }
+    }
+    /// Queues `buf` to be shown by `stream`, following the mode of the stream. Returns the id of
+    /// `buf`, a [`DmatexReleased`] event is sent once it was replaced (or dropped unshown).
+    pub fn submit(
+        &self,
+        images: &mut Assets<Image>,
+        stream: &DmabufStream,
+        buf: Dmatex,
+        usage: DmatexUsage,
+        on_drop: Option<Box<dyn FnOnce() + 'static + Send + Sync>>,
+    ) -> Result<DmatexId, ImportError> {
+        buf.validate()?;
+        let desc = get_imported_descriptor(&buf, usage)?;
+        // only touch the image if needed, changing it makes bevy prepare it again
+        if let Some(image) = images.get(&stream.handle)
+            && (image.texture_descriptor.size != desc.size
+                || image.texture_descriptor.format != view_format(&desc))
+            && let Some(image) = images.get_mut(&stream.handle)
+        {
+            image.texture_descriptor.size = desc.size;
+            image.texture_descriptor.format = view_format(&desc);
+        }
+        let release = self.new_release();
+        let id = release.id;
+        let frame = StreamFrame {
+            buf,
+            on_drop: DropCallback(on_drop),
+            usage,
+            release,
+        };
+        #[expect(clippy::unwrap_used)]
+        let mut imported = self.images.lock().unwrap();
+        match imported.get_mut(&stream.handle) {
+            Some(DmaImage::Stream(state)) => state.push(frame),
+            _ => {
+                let mut state = StreamState::new(stream.mode);
+                state.push(frame);
+                imported.insert(stream.handle.clone_weak(), DmaImage::Stream(state));
+            }
+        }
+        Ok(id)
     }
     pub fn insert_imported_dmatex(
         &self,
@@ -134,14 +414,14 @@ impl ImportedDmatexs {
             images.add(Image::new_uninit(
                 tex.texture.size(),
                 tex.texture.dimension(),
-                tex.texture.format(),
+                tex.view_format,
                 RenderAssetUsages::RENDER_WORLD,
             ))
         });
 
         let _span = debug_span!("inserting image handle").entered();
         #[expect(clippy::unwrap_used)]
-        self.0
+        self.images
             .lock()
             .unwrap()
             .insert(handle.clone_weak(), DmaImage::Imported(tex));
@@ -149,177 +429,147 @@ impl ImportedDmatexs {
     }
 }
 
-// idk if we need these?? you tell me
-
-// fn acquire_dmatex_images(world: &mut World) {
-//     let device = world.resource::<RenderDevice>();
-//     let dmatexs = world.resource::<ImportedDmatexs>();
-//     memory_barrier(device, dmatexs, ImageQueueTransfer::Acquire);
-// }
-// fn release_dmatex_images(world: &mut World) {
-//     let device = world.resource::<RenderDevice>();
-//     let dmatexs = world.resource::<ImportedDmatexs>();
-//     memory_barrier(device, dmatexs, ImageQueueTransfer::Release);
-// }
-
-// enum ImageQueueTransfer {
-//     Acquire,
-//     Release,
-// }
-
-// fn memory_barrier(
-//     device: &RenderDevice,
-//     dmatexs: &ImportedDmatexs,
-//     queue_transfer_direction: ImageQueueTransfer,
-// ) {
-//     unsafe {
-//         device.wgpu_device().as_hal::<Vulkan, _, _>(|dev| {
-//             let Some(dev) = dev else {
-//                 return;
-//             };
-//             let vk_dev = dev.raw_device();
-//             let Ok(command_pool) = vk_dev
-//                 .create_command_pool(
-//                     &vk::CommandPoolCreateInfo {
-//                         flags: vk::CommandPoolCreateFlags::TRANSIENT,
-//                         queue_family_index: dev.queue_family_index(),
-//                         ..Default::default()
-//                     },
-//                     None,
-//                 )
-//                 .inspect_err(|e| error!("Unable to create command pool: {e}"))
-//             else {
-//                 return;
-//             };
-
-//             let Ok(Some(buffer)) = dev
-//                 .raw_device()
-//                 .allocate_command_buffers(&vk::CommandBufferAllocateInfo {
-//                     command_pool,
-//                     level: vk::CommandBufferLevel::PRIMARY,
-//                     command_buffer_count: 1,
-//                     ..Default::default()
-//                 })
-//                 .inspect_err(|e| error!("Unable to allocate command buffer: {e}"))
-//                 .map(|v| v.into_iter().next())
-//             else {
-//                 vk_dev.destroy_command_pool(command_pool, None);
-//                 return;
-//             };
-//             let Ok(texes) = dmatexs
-//                 .0
-//                 .lock()
-//                 .inspect_err(|e| error!("Unable to lock dmatexs: {e}"))
-//             else {
-//                 vk_dev.destroy_command_pool(command_pool, None);
-//                 return;
-//             };
-
-//             vk_dev
-//                 .begin_command_buffer(
-//                     buffer,
-//                     &CommandBufferBeginInfo {
-//                         flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
-//                         ..Default::default()
-//                     },
-//                 )
-//                 .unwrap();
-
-//             let vk_submit_span = debug_span!("VK dmatex image acquire").entered();
-//             for image in texes
-//                 .iter()
-//                 .filter_map(|v| match v.1 {
-//                     DmaImage::UnImported(_, _, _) => None,
-//                     DmaImage::Imported(imported_texture) => Some(imported_texture),
-//                 })
-//                 .filter_map(|i| {
-//                     i.texture
-//                         .as_hal::<Vulkan, _, _>(|i| i.map(|i| i.raw_handle()))
-//                 })
-//             {
-//                 vk_dev.cmd_pipeline_barrier(
-//                     buffer,
-//                     vk::PipelineStageFlags::TOP_OF_PIPE,
-//                     vk::PipelineStageFlags::BOTTOM_OF_PIPE,
-//                     vk::DependencyFlags::empty(),
-//                     &[],
-//                     &[],
-//                     &[vk::ImageMemoryBarrier {
-//                         src_access_mask: vk::AccessFlags::NONE,
-//                         dst_access_mask: vk::AccessFlags::SHADER_READ,
-//                         old_layout: vk::ImageLayout::GENERAL,
-//                         new_layout: vk::ImageLayout::GENERAL,
-//                         // TODO: might want to use vk::QUEUE_FAMILY_FOREIGN_EXT instead
-//                         src_queue_family_index: match queue_transfer_direction {
-//                             ImageQueueTransfer::Acquire => vk::QUEUE_FAMILY_EXTERNAL,
-//                             ImageQueueTransfer::Release => dev.queue_family_index(),
-//                         },
-//                         dst_queue_family_index: match queue_transfer_direction {
-//                             ImageQueueTransfer::Acquire => dev.queue_family_index(),
-//                             ImageQueueTransfer::Release => vk::QUEUE_FAMILY_EXTERNAL,
-//                         },
-//                         image,
-//                         subresource_range: vk::ImageSubresourceRange {
-//                             aspect_mask: vk::ImageAspectFlags::COLOR,
-//                             base_mip_level: 0,
-//                             level_count: 1,
-//                             base_array_layer: 0,
-//                             layer_count: 1,
-//                         },
-//                         ..Default::default()
-//                     }],
-//                 );
-//             }
-//             drop(vk_submit_span);
-//             vk_dev.end_command_buffer(buffer).unwrap();
-//             let fence = vk_dev
-//                 .create_fence(
-//                     &vk::FenceCreateInfo {
-//                         flags: vk::FenceCreateFlags::empty(),
-//                         ..Default::default()
-//                     },
-//                     None,
-//                 )
-//                 .unwrap();
-//             vk_dev
-//                 .queue_submit(
-//                     dev.raw_queue(),
-//                     &[vk::SubmitInfo::default().command_buffers(&[buffer])],
-//                     fence,
-//                 )
-//                 .unwrap();
-//             vk_dev.wait_for_fences(&[fence], true, u64::MAX).unwrap();
-//             vk_dev.destroy_command_pool(command_pool, None);
-//         })
-//     };
-// }
+/// Identifies an import by the dmabufs and layout it was imported from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ImportCacheKey {
+    /// dmabuf, offset and stride of each plane
+    planes: Vec<(DmabufId, u32, i32)>,
+    format: u32,
+    modifier: u64,
+    res: (u32, u32),
+    flip_y: bool,
+    transform: DmatexTransform,
+    srgb: bool,
+    color_matrix: ColorMatrix,
+    color_range: ColorRange,
+    usage: DmatexUsage,
+    device: Option<u64>,
+    explicit_sync: bool,
+}
+
+impl ImportCacheKey {
+    /// None if a dmabuf can't be identified, fd numbers get reused so those can't be cached.
+    fn new(buf: &Dmatex, usage: DmatexUsage) -> Option<Self> {
+        let planes = buf
+            .planes
+            .iter()
+            .map(|plane| match DmabufId::of(plane.dmabuf_fd.as_fd()) {
+                DmabufId::Fd(_) => None,
+                id => Some((id, plane.offset, plane.stride)),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            planes,
+            format: buf.format,
+            modifier: buf.planes.first()?.modifier,
+            res: (buf.res.x, buf.res.y),
+            flip_y: buf.flip_y,
+            transform: buf.transform,
+            srgb: buf.srgb,
+            color_matrix: buf.color_matrix,
+            color_range: buf.color_range,
+            usage,
+            device: buf.device,
+            explicit_sync: buf.explicit_sync.is_some(),
+        })
+    }
+}
+
+/// Imports that are no longer used, least recently used first.
+#[derive(Resource)]
+struct ImportCache {
+    entries: Vec<ImportedTexture>,
+    limit: usize,
+}
+
+impl ImportCache {
+    fn new(limit: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(limit),
+            limit,
+        }
+    }
+    fn take(&mut self, key: &ImportCacheKey) -> Option<ImportedTexture> {
+        let i = self
+            .entries
+            .iter()
+            .position(|tex| tex.cache_key.as_deref() == Some(key))?;
+        Some(self.entries.remove(i))
+    }
+    fn insert(&mut self, tex: ImportedTexture) {
+        if self.limit == 0 || tex.cache_key.is_none() {
+            return;
+        }
+        if self.entries.len() == self.limit {
+            self.entries.remove(0);
+        }
+        self.entries.push(tex);
+    }
+}
+
+/// Explicitly synced dmatexs that are no longer used, their release point is signaled once the GPU
+/// is done with them and their acquire point was reached.
+#[derive(Resource, Default)]
+struct DmatexReleases {
+    /// added once the frames that showed them completed
+    idle: Arc<Mutex<Vec<Arc<ExplicitSync>>>>,
+    /// waiting for their acquire point
+    pending: Vec<Arc<ExplicitSync>>,
+}
 
 fn insert_dmatex_into_gpu_images(
     mut gpu_images: ResMut<RenderAssets<GpuImage>>,
     imported: Res<ImportedDmatexs>,
     device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    releases: Res<DmatexReleases>,
+    mut cache: ResMut<ImportCache>,
 ) {
     #[expect(clippy::unwrap_used)]
-    let mut imported = imported.0.lock().unwrap();
+    let mut imported = imported.images.lock().unwrap();
     let handles = imported.keys().cloned().collect::<Vec<_>>();
     for handle in handles {
         // filter out outdated dmatexs
         if gpu_images.get(&handle).is_none() {
-            imported.remove(&handle);
+            match imported.remove(&handle) {
+                Some(DmaImage::Imported(tex)) => retire_import(tex, &queue, &releases, &mut cache),
+                Some(DmaImage::Stream(StreamState {
+                    current: Some(tex), ..
+                })) => retire_import(tex, &queue, &releases, &mut cache),
+                _ => {}
+            }
             continue;
         }
-        if matches!(imported.get(&handle), Some(DmaImage::UnImported(_, _, _))) {
-            if let Some(DmaImage::UnImported(dmabuf, on_drop, usage)) = imported.remove(&handle) {
-                match import_texture(&device, dmabuf, on_drop, usage) {
-                    Ok(tex) => {
-                        debug!("imported dmatex");
-                        imported.insert(handle.clone(), DmaImage::Imported(tex));
-                    }
-                    Err(err) => {
-                        error!("failed to import dmatex: {err}");
-                        continue;
+        if let Some(DmaImage::Stream(stream)) = imported.get_mut(&handle)
+            && let Some(frame) = stream.next()
+        {
+            match import_cached_texture(&device, &mut cache, frame.buf, frame.on_drop, frame.usage)
+            {
+                Ok(mut tex) => {
+                    tex.release = Some(Arc::new(frame.release));
+                    if let Some(old) = stream.current.replace(tex) {
+                        retire_import(old, &queue, &releases, &mut cache);
                     }
                 }
+                Err(err) => error!("failed to import dmatex of stream: {err}"),
+            }
+        }
+        if matches!(
+            imported.get(&handle),
+            Some(DmaImage::UnImported(_, _, _, _))
+        ) && let Some(DmaImage::UnImported(dmabuf, on_drop, usage, release)) =
+            imported.remove(&handle)
+        {
+            match import_cached_texture(&device, &mut cache, dmabuf, on_drop, usage) {
+                Ok(mut tex) => {
+                    debug!("imported dmatex");
+                    tex.release = Some(Arc::new(release));
+                    imported.insert(handle.clone(), DmaImage::Imported(tex));
+                }
+                Err(err) => {
+                    error!("failed to import dmatex: {err}");
+                    continue;
+                }
             }
         }
         let Some(render_tex) = gpu_images.get_mut(&handle) else {
@@ -327,24 +577,341 @@ fn insert_dmatex_into_gpu_images(
             continue;
         };
 
-        if let Some(DmaImage::Imported(tex)) = imported.get(&handle) {
+        // streams show nothing until their first frame is imported
+        if let Some(tex) = imported.get(&handle).and_then(DmaImage::imported) {
             debug!("setting texture view!");
             render_tex.texture_view = tex.texture_view.clone();
+            render_tex.texture_format = tex.view_format;
             render_tex.size = tex.texture.size();
             render_tex.mip_level_count = tex.texture.mip_level_count();
             render_tex.texture = tex.texture.clone();
-        } else {
-            error!("unreachable");
         }
     }
 }
 
-fn get_handle(images: &mut Assets<Image>, buf: &Dmatex) -> Result<Handle<Image>, ImportError> {
-    let desc = get_imported_descriptor(buf)?;
+/// Hands an import that is no longer shown back to its producer, or keeps it for reuse.
+fn retire_import(
+    mut tex: ImportedTexture,
+    queue: &RenderQueue,
+    releases: &DmatexReleases,
+    cache: &mut ImportCache,
+) {
+    let sync = tex.explicit_sync.take();
+    let release = tex.release.take();
+    let on_drop = tex.cached_on_drop.take();
+    if sync.is_some() || release.is_some() || on_drop.is_some() {
+        let idle = releases.idle.clone();
+        // the frames before this one might still be reading it
+        queue.on_submitted_work_done(move || {
+            if let Some(sync) = sync {
+                #[expect(clippy::unwrap_used)]
+                idle.lock().unwrap().push(sync);
+            }
+            drop((release, on_drop));
+        });
+    }
+    cache.insert(tex);
+}
+
+/// Reuses the cached import of the dmabufs of `buf` or imports them.
+fn import_cached_texture(
+    device: &RenderDevice,
+    cache: &mut ImportCache,
+    buf: Dmatex,
+    on_drop: DropCallback,
+    usage: DmatexUsage,
+) -> Result<ImportedTexture, ImportError> {
+    // a dmatex matching a cached import can still be invalid, e.g. if the dmabuf shrank
+    let (wgpu_desc, info) = validate_import(device, &buf, usage)?;
+    let Some(key) = ImportCacheKey::new(&buf, usage) else {
+        return import_validated_texture(device, buf, wgpu_desc, info, on_drop, usage);
+    };
+    let mut tex = match cache.take(&key) {
+        Some(mut tex) => {
+            debug!("reusing cached dmatex import");
+            // the cached import keeps its own fds of the same dmabufs
+            tex.explicit_sync = buf
+                .explicit_sync
+                .as_ref()
+                .map(|sync| import_explicit_sync(device, sync))
+                .transpose()?;
+            tex
+        }
+        None => {
+            let mut tex =
+                import_validated_texture(device, buf, wgpu_desc, info, DropCallback(None), usage)?;
+            tex.cache_key = Some(Box::new(key));
+            tex
+        }
+    };
+    // the import outlives the dmatex in the cache, the dmatex is released once it's retired
+    tex.cached_on_drop = Some(Arc::new(on_drop));
+    Ok(tex)
+}
+
+/// How long rendering waits for the acquire point (or pending writes) of a dmatex before showing
+/// it anyway.
+const ACQUIRE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Waits for the acquire points of the dmatexs and, with implicit sync, for the pending writes to
+/// their dmabufs, then transfers them to the render queue.
+fn acquire_dmatexs(
+    imported: Res<ImportedDmatexs>,
+    transfers: Res<OwnershipTransfers>,
+    implicit_sync: Option<Res<ImplicitSync>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    #[expect(clippy::unwrap_used)]
+    let mut imported = imported.images.lock().unwrap();
+    for sync in imported
+        .values()
+        .filter_map(DmaImage::imported)
+        .filter_map(|tex| tex.explicit_sync.as_deref())
+    {
+        match sync.wait_acquire(ACQUIRE_TIMEOUT) {
+            Ok(true) => {}
+            Ok(false) => warn!("dmatex acquire point not reached within {ACQUIRE_TIMEOUT:?}"),
+            Err(err) => error!("unable to wait for dmatex acquire point: {err}"),
+        }
+    }
+    if let Some(implicit_sync) = implicit_sync {
+        match implicit_sync.wait_for_writes(implicitly_synced_dmabufs(&imported), ACQUIRE_TIMEOUT) {
+            Ok(true) => {}
+            Ok(false) => warn!("dmatex writes not done within {ACQUIRE_TIMEOUT:?}"),
+            Err(err) => error!("unable to wait for implicitly synced dmatexs: {err}"),
+        }
+    }
+    let texs = imported.values_mut().filter_map(DmaImage::imported_mut);
+    if let Err(err) = acquire_imports(&device, &queue, &transfers, texs) {
+        error!("unable to acquire dmatexs: {err}");
+    }
+}
+
+fn transfer_dmatexs_to_foreign_queue(
+    imported: Res<ImportedDmatexs>,
+    transfers: Res<OwnershipTransfers>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    #[expect(clippy::unwrap_used)]
+    let imported = imported.images.lock().unwrap();
+    let texs = imported.values().filter_map(DmaImage::imported);
+    release_imports(&device, &queue, &transfers, texs);
+}
+
+/// Transfers the images of `texs` from the foreign queue family to bevy's.
+///
+/// The contents of images wgpu hasn't used yet are backed up and restored after wgpu transitioned
+/// them from `UNDEFINED`. That transition doesn't wait for earlier commands, so the backup is
+/// waited for on the host.
+fn acquire_imports<'a>(
+    device: &RenderDevice,
+    queue: &RenderQueue,
+    transfers: &OwnershipTransfers,
+    texs: impl IntoIterator<Item = &'a mut ImportedTexture>,
+) -> Result<(), ImportError> {
+    let texs = texs.into_iter().collect::<Vec<_>>();
+    let images = texs
+        .iter()
+        .flat_map(|tex| tex.images.iter().map(|image| image.image))
+        .collect::<Vec<_>>();
+    if images.is_empty() {
+        return Ok(());
+    }
+    let fresh = texs
+        .into_iter()
+        .filter(|tex| !tex.initialized)
+        .flat_map(|tex| {
+            tex.initialized = true;
+            let tex: &'a ImportedTexture = tex;
+            &tex.images
+        })
+        .collect::<Vec<_>>();
+    let backup = if fresh.is_empty() {
+        None
+    } else {
+        let images = fresh
+            .iter()
+            .map(|image| {
+                let size = image.texture.size();
+                let texel_size = image
+                    .texture
+                    .format()
+                    .block_copy_size(None)
+                    .ok_or(ImportError::WgpuIncompatibleFormat)?;
+                Ok((
+                    image.image,
+                    vk::Extent2D::default()
+                        .width(size.width)
+                        .height(size.height),
+                    texel_size,
+                ))
+            })
+            .collect::<Result<Vec<_>, ImportError>>()?;
+        let backup = unsafe {
+            device.wgpu_device().as_hal::<Vulkan, _, _>(|dev| {
+                ContentsBackup::new(dev.ok_or(ImportError::NotVulkan)?, images)
+                    .map_err(ImportError::from)
+            })
+        }?;
+        Some(backup)
+    };
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("dmatex acquire encoder"),
+    });
+    unsafe {
+        transfers.record(&mut encoder, &images, OwnershipTransfer::Acquire);
+        if let Some(backup) = &backup {
+            transfers.record_backup(&mut encoder, backup);
+        }
+    }
+    let submission = queue.submit([encoder.finish()]);
+    let Some(backup) = backup else {
+        return Ok(());
+    };
+    device
+        .wgpu_device()
+        .poll(wgpu::Maintain::WaitForSubmissionIndex(submission));
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("dmatex restore encoder"),
+    });
+    record_copy_src_uses(
+        device,
+        &mut encoder,
+        fresh.iter().map(|image| &image.texture),
+    );
+    unsafe { transfers.record_restore(&mut encoder, &backup) };
+    queue.submit([encoder.finish()]);
+    // wgpu doesn't know the buffer is in use
+    queue.on_submitted_work_done(move || drop(backup));
+    Ok(())
+}
+
+/// Transfers the images of `texs` back to the foreign queue family.
+fn release_imports<'a>(
+    device: &RenderDevice,
+    queue: &RenderQueue,
+    transfers: &OwnershipTransfers,
+    texs: impl IntoIterator<Item = &'a ImportedTexture>,
+) {
+    let images = texs
+        .into_iter()
+        .flat_map(|tex| &tex.images)
+        .collect::<Vec<_>>();
+    if images.is_empty() {
+        return;
+    }
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("dmatex release encoder"),
+    });
+    record_copy_src_uses(
+        device,
+        &mut encoder,
+        images.iter().map(|image| &image.texture),
+    );
+    let images = images.iter().map(|image| image.image).collect::<Vec<_>>();
+    unsafe { transfers.record(&mut encoder, &images, OwnershipTransfer::Release) };
+    queue.submit([encoder.finish()]);
+}
+
+/// Copies a texel of each of `textures`, which makes wgpu track them as used for `COPY_SRC`, the
+/// state [`OwnershipTransfers`] expects.
+fn record_copy_src_uses<'a>(
+    device: &RenderDevice,
+    encoder: &mut wgpu::CommandEncoder,
+    textures: impl ExactSizeIterator<Item = &'a Texture>,
+) {
+    // a multiple of every texel size
+    const TEXEL_STRIDE: u64 = 16;
+    let scratch = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("dmatex scratch buffer"),
+        size: TEXEL_STRIDE * textures.len() as u64,
+        usage: wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    for (i, texture) in textures.enumerate() {
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &scratch,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: i as u64 * TEXEL_STRIDE,
+                    bytes_per_row: None,
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+fn implicitly_synced_dmabufs(
+    imported: &HashMap<Handle<Image>, DmaImage>,
+) -> impl Iterator<Item = BorrowedFd<'_>> {
+    imported
+        .values()
+        .filter_map(DmaImage::imported)
+        .filter(|tex| tex.explicit_sync.is_none())
+        .flat_map(|tex| tex.dmabufs.iter().map(|fd| fd.as_fd()))
+}
+
+/// Signals the release points from the host, the queue is only used through wgpu.
+fn release_dmatexs(mut releases: ResMut<DmatexReleases>) {
+    let releases = &mut *releases;
+    #[expect(clippy::unwrap_used)]
+    releases.pending.append(&mut releases.idle.lock().unwrap());
+    releases.pending.retain(|sync| match sync.signal_release() {
+        Ok(signaled) => !signaled,
+        Err(err) => {
+            error!("unable to signal dmatex release point: {err}");
+            false
+        }
+    });
+}
+
+fn convert_dmatexs(
+    imported: Res<ImportedDmatexs>,
+    pipelines: Res<ConvertPipelines>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    #[expect(clippy::unwrap_used)]
+    let imported = imported.images.lock().unwrap();
+    let mut encoder = None;
+    for source in imported
+        .values()
+        .filter_map(DmaImage::imported)
+        .filter_map(|tex| tex.convert.as_ref())
+    {
+        let encoder = encoder.get_or_insert_with(|| {
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("dmatex convert encoder"),
+            })
+        });
+        pipelines.record(&device, encoder, source);
+    }
+    if let Some(encoder) = encoder {
+        queue.submit([encoder.finish()]);
+    }
+}
+
+fn get_handle(
+    images: &mut Assets<Image>,
+    buf: &Dmatex,
+    usage: DmatexUsage,
+) -> Result<Handle<Image>, ImportError> {
+    let desc = get_imported_descriptor(buf, usage)?;
     Ok(images.add(Image::new_uninit(
         desc.size,
         desc.dimension,
-        desc.format,
+        view_format(&desc),
         RenderAssetUsages::RENDER_WORLD,
     )))
 }
@@ -357,8 +924,8 @@ pub enum ImportError {
     WgpuIncompatibleFormat,
     #[error("Wgpu Error: {0}")]
     Wgpu(#[from] wgpu::Error),
-    #[error("Unsupported Modifier for Format")]
-    ModifierInvalid,
+    #[error("Unsupported Modifier {0} for Format")]
+    ModifierInvalid(Modifier),
     #[error("Unrecognized Fourcc/Format")]
     UnrecognizedFourcc(#[from] drm_fourcc::UnrecognizedFourcc),
     #[error("RenderDevice is not a Vulkan Device")]
@@ -371,36 +938,188 @@ pub enum ImportError {
     IncorrectNumberOfPlanes,
     #[error("No Planes to Import")]
     NoPlanes,
+    #[error(transparent)]
+    InvalidDmatex(#[from] DmatexValidationError),
+    #[error("Dmatex resolution exceeds the maximum image extent for this format and modifier")]
+    ExtentTooLarge,
+    #[error("Stride {stride} of plane {plane} is smaller than the {min_stride} bytes of a row")]
+    StrideTooSmall {
+        plane: usize,
+        stride: i32,
+        min_stride: u64,
+    },
+    #[error("Plane {plane} ends at byte {end}, but its dmabuf is only {size} bytes large")]
+    PlaneOutOfBounds { plane: usize, end: u64, size: u64 },
+    #[error("Unable to duplicate dmabuf fd: {0}")]
+    DuplicateFd(std::io::Error),
+    #[error(
+        "Planes are in separate dmabufs, but the format and modifier don't support disjoint images"
+    )]
+    DisjointUnsupported,
+    #[error(
+        "Dmatex was allocated on device {dmatex_device:#x}, but rendering happens on {render_device:x?}"
+    )]
+    DeviceMismatch {
+        dmatex_device: u64,
+        render_device: DrmDevice,
+    },
+    #[error(
+        "Dmatexs of 24-bit formats are imported as 8-bit texels, which only works with the linear modifier, not {0}"
+    )]
+    PackedFormatNotLinear(Modifier),
+    #[error(
+        "Yuv dmatexs are imported plane by plane, which doesn't work with the compression planes of {0}"
+    )]
+    YuvModifierUnsupported(Modifier),
+    #[error("The {format:?} texture format requires the {features:?} wgpu features")]
+    MissingFeatures {
+        format: wgpu::TextureFormat,
+        features: wgpu::Features,
+    },
+    #[error(
+        "The {0:?} usage writes to the dmatex, but its format or transform requires converting it"
+    )]
+    WritesToConvertedDmatex(DmatexUsage),
+    #[error("Modifier {modifier} lacks the {features:?} features required by the usage")]
+    UsageUnsupported {
+        modifier: Modifier,
+        features: vk::FormatFeatureFlags,
+    },
+    #[error("Explicit sync requires VK_KHR_external_semaphore_fd with timeline semaphores")]
+    ExplicitSyncUnsupported,
+    #[error("Unable to duplicate syncobj fd: {0}")]
+    DuplicateSyncobjFd(std::io::Error),
+    #[error("Vulkan Error: {0}")]
+    Vulkan(#[from] vk::Result),
 }
 
-fn get_imported_descriptor(buf: &Dmatex) -> Result<wgpu::TextureDescriptor<'static>, ImportError> {
+fn get_imported_descriptor(
+    buf: &Dmatex,
+    usage: DmatexUsage,
+) -> Result<wgpu::TextureDescriptor<'static>, ImportError> {
+    let drm_format = buf.format;
+    // some formats are only known to format_mapping, not to drm-fourcc
+    if format_info(drm_format).is_none() {
+        DrmFourcc::try_from(drm_format).map_err(ImportError::UnrecognizedFourcc)?;
+    }
+    let usage = usage.texture_usages();
+    // yuv and packed dmatexs are converted into a regular texture by a compute pass
+    let (format, usage) = match fourcc_to_yuv(drm_format) {
+        Some(yuv) => (yuv.output_format, usage | TextureUsages::STORAGE_BINDING),
+        None => match fourcc_to_wgpu(drm_format).ok_or(ImportError::WgpuIncompatibleFormat)? {
+            WgpuFormat::Native(format) | WgpuFormat::Swizzled(format) => (format, usage),
+            WgpuFormat::Packed(packed) => (
+                packed.output_format(),
+                usage | TextureUsages::STORAGE_BINDING,
+            ),
+        },
+    };
+    // the texture holds the upright image
+    let mut size = buffer_size(buf);
+    if buf.transform.swaps_dimensions() {
+        std::mem::swap(&mut size.width, &mut size.height);
+    }
+    let view_formats = if buf.srgb {
+        wgpu_srgb_view_formats(format)
+    } else {
+        &[]
+    };
     Ok(wgpu::TextureDescriptor {
         label: None,
-        size: wgpu::Extent3d {
-            width: buf.res.x,
-            height: buf.res.y,
-            depth_or_array_layers: 1,
-        },
+        size,
         mip_level_count: 1,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
-        format: fourcc_to_wgpu(
-            DrmFourcc::try_from(buf.format).map_err(ImportError::UnrecognizedFourcc)?,
-        )
-        .ok_or(ImportError::WgpuIncompatibleFormat)?,
-        usage: TextureUsages::RENDER_ATTACHMENT
-            | TextureUsages::TEXTURE_BINDING
-            | TextureUsages::COPY_SRC
-            | TextureUsages::COPY_DST,
-        view_formats: &[],
+        format,
+        usage,
+        view_formats,
     })
 }
 
+/// The format imported textures are viewed as.
+fn view_format(desc: &wgpu::TextureDescriptor) -> wgpu::TextureFormat {
+    desc.view_formats.first().copied().unwrap_or(desc.format)
+}
+
+fn buffer_size(buf: &Dmatex) -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width: buf.res.x,
+        height: buf.res.y,
+        depth_or_array_layers: 1,
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ImportedTexture {
     texture: Texture,
     texture_view: TextureView,
     usage: DmatexUsage,
+    /// format of `texture_view`, the srgb variant of the texture format if requested
+    view_format: wgpu::TextureFormat,
+    /// false if the format has no alpha channel, the texture is opaque then
+    has_alpha: bool,
+    convert: Option<ConvertSource>,
+    explicit_sync: Option<Arc<ExplicitSync>>,
+    /// kept for implicit sync
+    dmabufs: Arc<[OwnedFd]>,
+    /// the imported images, `texture` itself or the sources of `convert`
+    images: Vec<ImportedImage>,
+    /// false until wgpu used the images, see [`acquire_imports`]
+    initialized: bool,
+    /// set for imports of [`ImportedDmatexs::set`], which are cached once no longer used
+    cache_key: Option<Box<ImportCacheKey>>,
+    /// the drop callback of the dmatex a cached import currently shows
+    cached_on_drop: Option<Arc<DropCallback>>,
+    /// notifies the producer once the dmatex shown by this import is released
+    release: Option<Arc<DmatexRelease>>,
+}
+
+/// A VkImage imported from the dmabufs and the texture wrapping it.
+#[derive(Clone, Debug)]
+struct ImportedImage {
+    image: vk::Image,
+    texture: Texture,
+}
+
+impl ImportedTexture {
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+    pub fn usage(&self) -> DmatexUsage {
+        self.usage
+    }
+    /// Whether the alpha channel of the texture is meaningful, materials can use
+    /// `AlphaMode::Opaque` if it isn't.
+    pub fn has_alpha(&self) -> bool {
+        self.has_alpha
+    }
+    /// Transfers the dmabufs from the queue family of their producer to bevy's, this has to
+    /// happen every frame before the texture is used. [`DmabufImportPlugin`] does this for the
+    /// dmatexs it shows, it's only needed for textures that are used directly.
+    pub fn acquire(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) -> Result<(), ImportError> {
+        let transfers = ownership_transfers(device)?;
+        acquire_imports(device, queue, &transfers, [self])
+    }
+    /// Transfers the dmabufs back to the queue family of their producer once this frame is done
+    /// with the texture, after [`Self::acquire`].
+    pub fn release(&self, device: &RenderDevice, queue: &RenderQueue) -> Result<(), ImportError> {
+        let transfers = ownership_transfers(device)?;
+        release_imports(device, queue, &transfers, [self]);
+        Ok(())
+    }
+}
+
+fn ownership_transfers(device: &RenderDevice) -> Result<OwnershipTransfers, ImportError> {
+    unsafe {
+        device
+            .wgpu_device()
+            .as_hal::<Vulkan, _, _>(|dev| dev.map(OwnershipTransfers::new))
+    }
+    .ok_or(ImportError::NotVulkan)
 }
 
 #[tracing::instrument(level = "debug", skip(device, on_drop))]
@@ -410,55 +1129,458 @@ pub fn import_texture(
     on_drop: DropCallback,
     usage: DmatexUsage,
 ) -> Result<ImportedTexture, ImportError> {
-    // import wgpu stuff here
+    let (wgpu_desc, info) = validate_import(device, &buf, usage)?;
+    import_validated_texture(device, buf, wgpu_desc, info, on_drop, usage)
+}
+
+/// [`import_texture`] for a `buf` that passed [`validate_import`].
+fn import_validated_texture(
+    device: &RenderDevice,
+    buf: Dmatex,
+    wgpu_desc: wgpu::TextureDescriptor<'static>,
+    info: DrmFormatInfo,
+    on_drop: DropCallback,
+    usage: DmatexUsage,
+) -> Result<ImportedTexture, ImportError> {
+    let drm_format = buf.format;
+    let has_alpha = info.has_alpha;
+    // wgpu can't swizzle views, so the undefined X channel of formats without alpha is replaced
+    // while copying the dmatex. yuv and packed formats are converted into opaque textures anyway
+    let opaque = !has_alpha && wgpu_desc.format.components() == 4;
+    let swizzled = matches!(fourcc_to_wgpu(drm_format), Some(WgpuFormat::Swizzled(_)));
+    let converted = fourcc_to_yuv(drm_format).is_some()
+        || matches!(fourcc_to_wgpu(drm_format), Some(WgpuFormat::Packed(_)))
+        || swizzled
+        || buf.flip_y
+        || buf.transform != DmatexTransform::Normal
+        || opaque;
+    // bevy renders into a copy of converted dmatexs, writes would never reach the dmabuf
+    if converted && usage.intersects(DmatexUsage::WRITES) {
+        return Err(ImportError::WritesToConvertedDmatex(
+            usage & DmatexUsage::WRITES,
+        ));
+    }
+    let explicit_sync = buf
+        .explicit_sync
+        .as_ref()
+        .map(|sync| import_explicit_sync(device, sync))
+        .transpose()?;
+    let mut tex = match (fourcc_to_yuv(drm_format), fourcc_to_wgpu(drm_format)) {
+        (Some(yuv), _) => import_yuv_texture(device, &buf, &yuv, &wgpu_desc, on_drop, usage)?,
+        (None, Some(WgpuFormat::Packed(packed))) => {
+            import_packed_texture(device, &buf, &packed, &wgpu_desc, on_drop, usage)?
+        }
+        _ if swizzled || buf.flip_y || buf.transform != DmatexTransform::Normal || opaque => {
+            import_blit_texture(device, &buf, &wgpu_desc, opaque, swizzled, on_drop, usage)?
+        }
+        _ => {
+            let vk_format =
+                drm_fourcc_to_vk_format(drm_format).ok_or(ImportError::VulkanIncompatibleFormat)?;
+            let (texture, image) =
+                import_raw_texture(device, &buf.planes, vk_format, &wgpu_desc, on_drop)?;
+            let texture_view = create_texture_view(&texture, view_format(&wgpu_desc));
+            let image = ImportedImage {
+                image,
+                texture: texture.clone(),
+            };
+            ImportedTexture {
+                texture,
+                texture_view,
+                view_format: view_format(&wgpu_desc),
+                has_alpha: true,
+                usage,
+                convert: None,
+                explicit_sync: None,
+                dmabufs: Arc::new([]),
+                images: vec![image],
+                initialized: false,
+                cache_key: None,
+                cached_on_drop: None,
+                release: None,
+            }
+        }
+    };
+    tex.has_alpha = has_alpha;
+    tex.explicit_sync = explicit_sync;
+    tex.dmabufs = buf
+        .planes
+        .into_iter()
+        .map(|plane| plane.dmabuf_fd.into())
+        .collect();
+    Ok(tex)
+}
+
+/// Checks `buf` before anything is handed to Vulkan, returns the descriptor of the texture it's
+/// imported as and the memory layout of its format.
+fn validate_import(
+    device: &RenderDevice,
+    buf: &Dmatex,
+    usage: DmatexUsage,
+) -> Result<(wgpu::TextureDescriptor<'static>, DrmFormatInfo), ImportError> {
+    buf.validate()?;
+    let wgpu_desc = get_imported_descriptor(buf, usage)?;
+    let info = format_info(buf.format).ok_or(ImportError::WgpuIncompatibleFormat)?;
+    validate_planes(buf, &info)?;
+    if let Some(dmatex_device) = buf.device
+        && let Some(render_device) = DrmDevice::query(device)
+        && !render_device.contains(dmatex_device)
+    {
+        return Err(ImportError::DeviceMismatch {
+            dmatex_device,
+            render_device,
+        });
+    }
+    Ok((wgpu_desc, info))
+}
+
+/// Checks the planes of `buf` against the memory layout of its format, before anything is
+/// handed to Vulkan.
+fn validate_planes(buf: &Dmatex, info: &DrmFormatInfo) -> Result<(), ImportError> {
+    if buf.planes.is_empty() {
+        return Err(ImportError::NoPlanes);
+    }
+    // every plane has the same modifier, see Dmatex::validate
+    let modifier = Modifier(buf.planes[0].modifier);
+    // yuv planes are imported one by one, see import_yuv_texture
+    if info.yuv && modifier.has_aux_plane() {
+        return Err(ImportError::YuvModifierUnsupported(modifier));
+    }
+    // compression metadata comes in auxiliary planes after the color planes
+    let plane_count_matches = if modifier.has_aux_plane() {
+        buf.planes.len() > info.plane_count()
+    } else {
+        buf.planes.len() == info.plane_count()
+    };
+    if !plane_count_matches {
+        return Err(ImportError::IncorrectNumberOfPlanes);
+    }
+    for (i, plane) in buf.planes.iter().enumerate().take(info.plane_count()) {
+        let min_stride = info.min_stride(i, buf.res.x);
+        let stride = u64::try_from(plane.stride).unwrap_or(0);
+        if stride < min_stride {
+            return Err(ImportError::StrideTooSmall {
+                plane: i,
+                stride: plane.stride,
+                min_stride,
+            });
+        }
+        let (_, height) = info.plane_size(i, buf.res.x, buf.res.y);
+        let end =
+            u64::from(plane.offset) + stride * u64::from(height.saturating_sub(1)) + min_stride;
+        if let Some(size) = dmabuf_size(plane.dmabuf_fd.as_fd())
+            && end > size
+        {
+            return Err(ImportError::PlaneOutOfBounds {
+                plane: i,
+                end,
+                size,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Size of a dmabuf in bytes, [`None`] if the fd can't be seeked.
+fn dmabuf_size(fd: BorrowedFd) -> Option<u64> {
+    // the file offset is shared with every dup of the fd, including the producer's
+    let offset = unsafe { libc::lseek(fd.as_raw_fd(), 0, libc::SEEK_CUR) };
+    let size = unsafe { libc::lseek(fd.as_raw_fd(), 0, libc::SEEK_END) };
+    if offset >= 0 {
+        unsafe { libc::lseek(fd.as_raw_fd(), offset, libc::SEEK_SET) };
+    }
+    u64::try_from(size).ok()
+}
+
+fn import_explicit_sync(
+    device: &RenderDevice,
+    sync: &DmatexExplicitSync,
+) -> Result<Arc<ExplicitSync>, ImportError> {
+    let sync = unsafe {
+        device.wgpu_device().as_hal::<Vulkan, _, _>(|dev| {
+            ExplicitSync::import(dev.ok_or(ImportError::NotVulkan)?, sync)
+        })
+    }?;
+    Ok(Arc::new(sync))
+}
+
+/// Imports every plane of a yuv dmatex as its own texture and creates the rgb texture they get
+/// converted into. Splitting the planes only works for modifiers that tile every plane on its own,
+/// modifiers with compression metadata describe the whole multi-planar image and are rejected.
+fn import_yuv_texture(
+    device: &RenderDevice,
+    buf: &Dmatex,
+    yuv: &YuvFormat,
+    desc: &wgpu::TextureDescriptor<'static>,
+    on_drop: DropCallback,
+    usage: DmatexUsage,
+) -> Result<ImportedTexture, ImportError> {
+    if buf.planes.is_empty() {
+        return Err(ImportError::NoPlanes);
+    }
+    if buf.planes.len() != yuv.planes.len() {
+        return Err(ImportError::IncorrectNumberOfPlanes);
+    }
+    // the first plane texture owns the drop callback, the views of every plane outlive the texture
+    // so the callback only runs once all planes are gone
+    let mut on_drop = Some(on_drop);
+    let planes = buf
+        .planes
+        .iter()
+        .zip(yuv.planes)
+        .map(|(plane, plane_format)| {
+            let (div_x, div_y) = plane_format.subsampling;
+            let plane_desc = wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: buf.res.x.div_ceil(div_x),
+                    height: buf.res.y.div_ceil(div_y),
+                    depth_or_array_layers: 1,
+                },
+                format: plane_format.wgpu_format,
+                usage: TextureUsages::TEXTURE_BINDING,
+                ..desc.clone()
+            };
+            let (texture, image) = import_raw_texture(
+                device,
+                std::slice::from_ref(plane),
+                plane_format.vk_format,
+                &plane_desc,
+                on_drop.take().unwrap_or(DropCallback(None)),
+            )?;
+            let view = create_texture_view(&texture, texture.format());
+            Ok((view, ImportedImage { image, texture }))
+        })
+        .collect::<Result<Vec<_>, ImportError>>()?;
+    let (planes, images) = planes.into_iter().unzip();
+
+    let texture = device.create_texture(desc);
+    let texture_view = create_texture_view(&texture, view_format(desc));
+    let convert = ConvertSource::new_yuv(device, planes, yuv, buf, &texture);
+    Ok(ImportedTexture {
+        texture,
+        texture_view,
+        view_format: view_format(desc),
+        has_alpha: true,
+        usage,
+        convert: Some(convert),
+        explicit_sync: None,
+        dmabufs: Arc::new([]),
+        images,
+        initialized: false,
+        cache_key: None,
+        cached_on_drop: None,
+        release: None,
+    })
+}
+
+/// Imports a dmatex of a packed format wgpu has no equivalent for as raw integer texels and
+/// creates the rgba texture they get unpacked into.
+fn import_packed_texture(
+    device: &RenderDevice,
+    buf: &Dmatex,
+    packed: &PackedFormat,
+    desc: &wgpu::TextureDescriptor<'static>,
+    on_drop: DropCallback,
+    usage: DmatexUsage,
+) -> Result<ImportedTexture, ImportError> {
+    // the tiling of a modifier depends on the texel size, so splitting pixels into multiple
+    // texels only keeps the layout intact for linear buffers
+    if packed.texels_per_pixel() > 1
+        && let Some(plane) = buf
+            .planes
+            .iter()
+            .find(|plane| !Modifier(plane.modifier).is_linear())
+    {
+        return Err(ImportError::PackedFormatNotLinear(Modifier(plane.modifier)));
+    }
+    let (raw_format, raw_vk_format) = packed.raw_format();
+    let mut size = buffer_size(buf);
+    size.width *= packed.texels_per_pixel();
+    let source_desc = wgpu::TextureDescriptor {
+        size,
+        format: raw_format,
+        usage: TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+        ..desc.clone()
+    };
+    let (source, image) =
+        import_raw_texture(device, &buf.planes, raw_vk_format, &source_desc, on_drop)?;
+    let image = ImportedImage {
+        image,
+        texture: source.clone(),
+    };
+
+    let texture = device.create_texture(desc);
+    let texture_view = create_texture_view(&texture, view_format(desc));
+    let convert = ConvertSource::new_unpack(
+        device,
+        create_texture_view(&source, source.format()),
+        packed,
+        buf,
+        &texture,
+    );
+    Ok(ImportedTexture {
+        texture,
+        texture_view,
+        view_format: view_format(desc),
+        has_alpha: true,
+        usage,
+        convert: Some(convert),
+        explicit_sync: None,
+        dmabufs: Arc::new([]),
+        images: vec![image],
+        initialized: false,
+        cache_key: None,
+        cached_on_drop: None,
+        release: None,
+    })
+}
+
+/// Imports a dmatex that has to be transformed, made opaque or swizzled, it's copied into a texture
+/// owned by bevy every frame.
+fn import_blit_texture(
+    device: &RenderDevice,
+    buf: &Dmatex,
+    desc: &wgpu::TextureDescriptor<'static>,
+    opaque: bool,
+    swizzled: bool,
+    on_drop: DropCallback,
+    usage: DmatexUsage,
+) -> Result<ImportedTexture, ImportError> {
+    let vk_format =
+        drm_fourcc_to_vk_format(buf.format).ok_or(ImportError::VulkanIncompatibleFormat)?;
+    let source_desc = wgpu::TextureDescriptor {
+        size: buffer_size(buf),
+        usage: TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+        ..desc.clone()
+    };
+    let (source, image) =
+        import_raw_texture(device, &buf.planes, vk_format, &source_desc, on_drop)?;
+    let image = ImportedImage {
+        image,
+        texture: source.clone(),
+    };
+
+    // the blit renders into the texture
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        usage: desc.usage | TextureUsages::RENDER_ATTACHMENT,
+        ..desc.clone()
+    });
+    let texture_view = create_texture_view(&texture, view_format(desc));
+    let convert = ConvertSource::new_blit(
+        device,
+        create_texture_view(&source, source.format()),
+        buf,
+        opaque,
+        swizzled,
+        &texture,
+    );
+    Ok(ImportedTexture {
+        texture,
+        texture_view,
+        view_format: view_format(desc),
+        has_alpha: true,
+        usage,
+        convert: Some(convert),
+        explicit_sync: None,
+        dmabufs: Arc::new([]),
+        images: vec![image],
+        initialized: false,
+        cache_key: None,
+        cached_on_drop: None,
+        release: None,
+    })
+}
+
+/// Imports `planes` as a single VkImage of `vk_format` and wraps it in a wgpu texture described by
+/// `desc`.
+fn import_raw_texture(
+    device: &RenderDevice,
+    planes: &[DmatexPlane],
+    vk_format: vk::Format,
+    desc: &wgpu::TextureDescriptor<'static>,
+    on_drop: DropCallback,
+) -> Result<(Texture, vk::Image), ImportError> {
+    // the ownership transfers copy from and to every imported image
+    let desc = &wgpu::TextureDescriptor {
+        usage: desc.usage | TRANSFER_USAGES,
+        ..desc.clone()
+    };
+    // formats like Rgba16Unorm are optional in wgpu
+    let features = desc.format.required_features() - device.features();
+    if !features.is_empty() {
+        return Err(ImportError::MissingFeatures {
+            format: desc.format,
+            features,
+        });
+    }
+    let extent = vk::Extent3D {
+        width: desc.size.width,
+        height: desc.size.height,
+        depth: 1,
+    };
+    // the image has to be created with every format it will be viewed as
+    let vk_view_formats = if desc.view_formats.is_empty() {
+        vec![]
+    } else {
+        vec![
+            vk_format,
+            vk_format_to_srgb(vk_format).ok_or(ImportError::VulkanIncompatibleFormat)?,
+        ]
+    };
+    let (vk_dev, image, mems) = unsafe {
+        device
+            .wgpu_device()
+            .as_hal::<Vulkan, _, _>(|dev| -> Result<_, ImportError> {
+                let dev = dev.ok_or(ImportError::NotVulkan)?;
+                let (image, mems) = import_vk_image(
+                    dev,
+                    planes,
+                    vk_format,
+                    &vk_view_formats,
+                    vk_image_usage(desc.usage),
+                    extent,
+                )?;
+                Ok((dev.raw_device().clone(), image, mems))
+            })
+    }?;
 
     let descriptor = TextureDescriptor {
         label: None,
-        size: wgpu::Extent3d {
-            width: buf.res.x,
-            height: buf.res.y,
-            depth_or_array_layers: 1,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: wgpu::TextureDimension::D2,
-        format: fourcc_to_wgpu(
-            DrmFourcc::try_from(buf.format).map_err(ImportError::UnrecognizedFourcc)?,
-        )
-        .ok_or(ImportError::WgpuIncompatibleFormat)?,
-        usage: TextureUses::COLOR_TARGET | TextureUses::PRESENT,
+        size: desc.size,
+        mip_level_count: desc.mip_level_count,
+        sample_count: desc.sample_count,
+        dimension: desc.dimension,
+        format: desc.format,
+        usage: hal_texture_uses(desc.usage),
         memory_flags: MemoryFlags::empty(),
-        view_formats: vec![],
+        view_formats: desc.view_formats.to_vec(),
     };
     let texture = unsafe {
         wgpu::hal::vulkan::Device::texture_from_raw(
             image,
             &descriptor,
-            Some({
-                let dev = device.clone();
-                Box::new(move || {
-                    let _on_drop = on_drop;
-                    dev.wgpu_device().as_hal::<Vulkan, _, _>(move |dev| {
-                        if let Some(dev) = dev {
-                            for (mem, _) in mem {
-                                dev.raw_device().free_memory(mem, None);
-                            }
-                            dev.raw_device().destroy_image(image, None);
-                        }
-                    });
-                })
-            }),
+            Some(Box::new(move || {
+                let _on_drop = on_drop;
+                vk_dev.destroy_image(image, None);
+                for mem in mems {
+                    vk_dev.free_memory(mem, None);
+                }
+            })),
         )
     };
     let wgpu_texture = unsafe {
         device
             .wgpu_device()
-            .create_texture_from_hal::<Vulkan>(texture, &wgpu_desc)
+            .create_texture_from_hal::<Vulkan>(texture, desc)
     };
-    let texture = Texture::from(wgpu_texture);
-    let texture_view = texture.create_view(&TextureViewDescriptor {
+    Ok((Texture::from(wgpu_texture), image))
+}
+
+fn create_texture_view(texture: &Texture, format: wgpu::TextureFormat) -> TextureView {
+    texture.create_view(&TextureViewDescriptor {
         label: None,
-        format: Some(texture.format()),
+        format: Some(format),
         dimension: Some(wgpu::TextureViewDimension::D2),
         usage: Some(texture.usage()),
         aspect: wgpu::TextureAspect::All,
@@ -466,10 +1588,421 @@ pub fn import_texture(
         mip_level_count: Some(texture.mip_level_count()),
         base_array_layer: 0,
         array_layer_count: Some(texture.depth_or_array_layers()),
-    });
-    Ok(ImportedTexture {
-        texture,
-        texture_view,
-        usage,
     })
 }
+
+/// Usages every imported image has besides the requested ones, see [`OwnershipTransfers`].
+pub(crate) const TRANSFER_USAGES: TextureUsages =
+    TextureUsages::COPY_SRC.union(TextureUsages::COPY_DST);
+
+/// The VkImage usage matching the wgpu usage of an imported or exported texture.
+pub(crate) fn vk_image_usage(usage: TextureUsages) -> vk::ImageUsageFlags {
+    let mut flags = vk::ImageUsageFlags::empty();
+    if usage.contains(TextureUsages::TEXTURE_BINDING) {
+        flags |= vk::ImageUsageFlags::SAMPLED;
+    }
+    if usage.contains(TextureUsages::RENDER_ATTACHMENT) {
+        flags |= vk::ImageUsageFlags::COLOR_ATTACHMENT;
+    }
+    if usage.contains(TextureUsages::STORAGE_BINDING) {
+        flags |= vk::ImageUsageFlags::STORAGE;
+    }
+    if usage.contains(TextureUsages::COPY_SRC) {
+        flags |= vk::ImageUsageFlags::TRANSFER_SRC;
+    }
+    if usage.contains(TextureUsages::COPY_DST) {
+        flags |= vk::ImageUsageFlags::TRANSFER_DST;
+    }
+    flags
+}
+
+/// The format features an image with `usage` needs.
+fn usage_format_features(usage: vk::ImageUsageFlags) -> vk::FormatFeatureFlags {
+    [
+        (
+            vk::ImageUsageFlags::SAMPLED,
+            vk::FormatFeatureFlags::SAMPLED_IMAGE,
+        ),
+        (
+            vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            vk::FormatFeatureFlags::COLOR_ATTACHMENT,
+        ),
+        (
+            vk::ImageUsageFlags::STORAGE,
+            vk::FormatFeatureFlags::STORAGE_IMAGE,
+        ),
+        (
+            vk::ImageUsageFlags::TRANSFER_SRC,
+            vk::FormatFeatureFlags::TRANSFER_SRC,
+        ),
+        (
+            vk::ImageUsageFlags::TRANSFER_DST,
+            vk::FormatFeatureFlags::TRANSFER_DST,
+        ),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| usage.contains(*flag))
+    .fold(vk::FormatFeatureFlags::empty(), |features, (_, feature)| {
+        features | feature
+    })
+}
+
+pub(crate) fn hal_texture_uses(usage: TextureUsages) -> TextureUses {
+    let mut uses = TextureUses::empty();
+    if usage.contains(TextureUsages::TEXTURE_BINDING) {
+        uses |= TextureUses::RESOURCE;
+    }
+    if usage.contains(TextureUsages::RENDER_ATTACHMENT) {
+        uses |= TextureUses::COLOR_TARGET;
+    }
+    if usage.contains(TextureUsages::STORAGE_BINDING) {
+        uses |= TextureUses::STORAGE_READ_ONLY
+            | TextureUses::STORAGE_WRITE_ONLY
+            | TextureUses::STORAGE_READ_WRITE;
+    }
+    if usage.contains(TextureUsages::COPY_SRC) {
+        uses |= TextureUses::COPY_SRC;
+    }
+    if usage.contains(TextureUsages::COPY_DST) {
+        uses |= TextureUses::COPY_DST;
+    }
+    uses
+}
+
+/// Creates a VkImage with the explicit DRM format modifier layout of `planes` and binds the
+/// imported dmabuf memory to it. Planes in different dmabufs make the image disjoint, with one
+/// VkDeviceMemory per dmabuf. Everything created here is destroyed again if a later step fails.
+unsafe fn import_vk_image(
+    dev: &wgpu::hal::vulkan::Device,
+    planes: &[DmatexPlane],
+    format: vk::Format,
+    view_formats: &[vk::Format],
+    usage: vk::ImageUsageFlags,
+    extent: vk::Extent3D,
+) -> Result<(vk::Image, Vec<vk::DeviceMemory>), ImportError> {
+    let first_plane = planes.first().ok_or(ImportError::NoPlanes)?;
+    let modifier = first_plane.modifier;
+    let instance = dev.shared_instance().raw_instance();
+    let phys_dev = dev.raw_physical_device();
+    let vk_dev = dev.raw_device();
+
+    let modifier_props = get_drm_modifiers(instance, phys_dev, format)
+        .1
+        .into_iter()
+        .find(|props| props.drm_format_modifier == modifier)
+        .ok_or(ImportError::ModifierInvalid(Modifier(modifier)))?;
+    if modifier_props.drm_format_modifier_plane_count as usize != planes.len() {
+        return Err(ImportError::IncorrectNumberOfPlanes);
+    }
+    let dmabufs = unique_dmabufs(planes);
+    let disjoint = dmabufs.len() > 1;
+    if disjoint
+        && !modifier_props
+            .drm_format_modifier_tiling_features
+            .contains(vk::FormatFeatureFlags::DISJOINT)
+    {
+        return Err(ImportError::DisjointUnsupported);
+    }
+    let flags = if disjoint {
+        vk::ImageCreateFlags::DISJOINT
+    } else {
+        vk::ImageCreateFlags::empty()
+    };
+    let features =
+        usage_format_features(usage) & !modifier_props.drm_format_modifier_tiling_features;
+    if !features.is_empty() {
+        return Err(ImportError::UsageUnsupported {
+            modifier: Modifier(modifier),
+            features,
+        });
+    }
+
+    unsafe {
+        check_image_format_support(
+            instance,
+            phys_dev,
+            format,
+            view_formats,
+            modifier,
+            usage,
+            extent,
+        )
+    }?;
+
+    let plane_layouts = planes
+        .iter()
+        .map(|plane| vk::SubresourceLayout {
+            offset: plane.offset as u64,
+            size: 0,
+            row_pitch: plane.stride as u64,
+            array_pitch: 0,
+            depth_pitch: 0,
+        })
+        .collect::<Vec<_>>();
+    let mut modifier_info = vk::ImageDrmFormatModifierExplicitCreateInfoEXT::default()
+        .drm_format_modifier(modifier)
+        .plane_layouts(&plane_layouts);
+    let mut external_info = vk::ExternalMemoryImageCreateInfo::default()
+        .handle_types(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT);
+    let mut format_list = vk::ImageFormatListCreateInfo::default().view_formats(view_formats);
+    let image_info = vk::ImageCreateInfo::default()
+        .flags(mutable_format_flags(view_formats) | flags)
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(format)
+        .extent(extent)
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT)
+        .usage(usage)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .push_next(&mut modifier_info)
+        .push_next(&mut external_info)
+        .push_next(&mut format_list);
+    let image = unsafe { vk_dev.create_image(&image_info, None) }?;
+
+    let result = if disjoint {
+        unsafe { import_disjoint_memory(dev, image, planes, &dmabufs) }
+    } else {
+        unsafe { import_memory(dev, image, first_plane) }.map(|mem| vec![mem])
+    };
+    result.map(|mems| (image, mems)).inspect_err(|_| {
+        unsafe { vk_dev.destroy_image(image, None) };
+    })
+}
+
+/// Groups plane indices by the dmabuf they reference, fds duplicated from each other count as the
+/// same dmabuf.
+fn unique_dmabufs(planes: &[DmatexPlane]) -> Vec<(DmabufId, Vec<usize>)> {
+    let mut dmabufs: Vec<(DmabufId, Vec<usize>)> = Vec::new();
+    for (i, plane) in planes.iter().enumerate() {
+        let id = DmabufId::of(plane.dmabuf_fd.as_fd());
+        match dmabufs.iter_mut().find(|(other, _)| *other == id) {
+            Some((_, indices)) => indices.push(i),
+            None => dmabufs.push((id, vec![i])),
+        }
+    }
+    dmabufs
+}
+
+const MEMORY_PLANE_ASPECTS: [vk::ImageAspectFlags; 4] = [
+    vk::ImageAspectFlags::MEMORY_PLANE_0_EXT,
+    vk::ImageAspectFlags::MEMORY_PLANE_1_EXT,
+    vk::ImageAspectFlags::MEMORY_PLANE_2_EXT,
+    vk::ImageAspectFlags::MEMORY_PLANE_3_EXT,
+];
+
+/// Imports every dmabuf once and binds each memory plane of the disjoint `image` to the memory of
+/// its dmabuf.
+unsafe fn import_disjoint_memory(
+    dev: &wgpu::hal::vulkan::Device,
+    image: vk::Image,
+    planes: &[DmatexPlane],
+    dmabufs: &[(DmabufId, Vec<usize>)],
+) -> Result<Vec<vk::DeviceMemory>, ImportError> {
+    let vk_dev = dev.raw_device();
+    let mut mems = Vec::with_capacity(dmabufs.len());
+    let mut bind_infos = Vec::with_capacity(planes.len());
+    for (_, indices) in dmabufs {
+        // the plane layouts hold the offsets, so every plane of a dmabuf binds at offset 0 and the
+        // memory has to cover the largest requirement among them
+        let mut requirements = vk::MemoryRequirements {
+            memory_type_bits: !0,
+            ..default()
+        };
+        for &i in indices {
+            let mut plane_info = vk::ImagePlaneMemoryRequirementsInfo::default()
+                .plane_aspect(MEMORY_PLANE_ASPECTS[i]);
+            let info = vk::ImageMemoryRequirementsInfo2::default()
+                .image(image)
+                .push_next(&mut plane_info);
+            let mut plane_requirements = vk::MemoryRequirements2::default();
+            unsafe { vk_dev.get_image_memory_requirements2(&info, &mut plane_requirements) };
+            let plane_requirements = plane_requirements.memory_requirements;
+            requirements.size = requirements.size.max(plane_requirements.size);
+            requirements.memory_type_bits &= plane_requirements.memory_type_bits;
+        }
+        match unsafe { allocate_imported_memory(dev, None, requirements, &planes[indices[0]]) } {
+            Ok(mem) => mems.push(mem),
+            Err(err) => {
+                free_memories(vk_dev, &mems);
+                return Err(err);
+            }
+        }
+        for &i in indices {
+            bind_infos.push((mems.len() - 1, MEMORY_PLANE_ASPECTS[i]));
+        }
+    }
+
+    let mut plane_infos = bind_infos
+        .iter()
+        .map(|&(_, aspect)| vk::BindImagePlaneMemoryInfo::default().plane_aspect(aspect))
+        .collect::<Vec<_>>();
+    let bind_infos = bind_infos
+        .iter()
+        .zip(&mut plane_infos)
+        .map(|(&(mem, _), plane_info)| {
+            vk::BindImageMemoryInfo::default()
+                .image(image)
+                .memory(mems[mem])
+                .memory_offset(0)
+                .push_next(plane_info)
+        })
+        .collect::<Vec<_>>();
+    if let Err(err) = unsafe { vk_dev.bind_image_memory2(&bind_infos) } {
+        free_memories(vk_dev, &mems);
+        return Err(err.into());
+    }
+    Ok(mems)
+}
+
+fn free_memories(vk_dev: &ash::Device, mems: &[vk::DeviceMemory]) {
+    for &mem in mems {
+        unsafe { vk_dev.free_memory(mem, None) };
+    }
+}
+
+/// Imports the dmabuf of `plane` as dedicated memory for `image` and binds it.
+unsafe fn import_memory(
+    dev: &wgpu::hal::vulkan::Device,
+    image: vk::Image,
+    plane: &DmatexPlane,
+) -> Result<vk::DeviceMemory, ImportError> {
+    let vk_dev = dev.raw_device();
+    let requirements = unsafe { vk_dev.get_image_memory_requirements(image) };
+    let mem = unsafe { allocate_imported_memory(dev, Some(image), requirements, plane) }?;
+    if let Err(err) = unsafe { vk_dev.bind_image_memory(image, mem, 0) } {
+        unsafe { vk_dev.free_memory(mem, None) };
+        return Err(err.into());
+    }
+    Ok(mem)
+}
+
+/// Imports the dmabuf of `plane` as memory fulfilling `requirements`, dedicated to `image` if
+/// given.
+unsafe fn allocate_imported_memory(
+    dev: &wgpu::hal::vulkan::Device,
+    dedicated_image: Option<vk::Image>,
+    requirements: vk::MemoryRequirements,
+    plane: &DmatexPlane,
+) -> Result<vk::DeviceMemory, ImportError> {
+    let instance = dev.shared_instance().raw_instance();
+    let vk_dev = dev.raw_device();
+    let memory_fd = ash::khr::external_memory_fd::Device::new(instance, vk_dev);
+
+    let mut fd_props = vk::MemoryFdPropertiesKHR::default();
+    unsafe {
+        memory_fd.get_memory_fd_properties(
+            vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+            plane.dmabuf_fd.as_raw_fd(),
+            &mut fd_props,
+        )
+    }?;
+    let memory_type_bits = requirements.memory_type_bits & fd_props.memory_type_bits;
+    if memory_type_bits == 0 {
+        return Err(ImportError::NoValidMemoryTypes);
+    }
+    let memory_type_index = memory_type_bits.trailing_zeros();
+
+    // on success vulkan takes ownership of the fd, so hand it a duplicate
+    let fd = plane
+        .dmabuf_fd
+        .as_fd()
+        .try_clone_to_owned()
+        .map_err(ImportError::DuplicateFd)?;
+    let mut import_info = vk::ImportMemoryFdInfoKHR::default()
+        .handle_type(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT)
+        .fd(fd.as_raw_fd());
+    let mut alloc_info = vk::MemoryAllocateInfo::default()
+        .allocation_size(requirements.size)
+        .memory_type_index(memory_type_index)
+        .push_next(&mut import_info);
+    // disjoint images can't have dedicated allocations
+    let mut dedicated_info =
+        dedicated_image.map(|image| vk::MemoryDedicatedAllocateInfo::default().image(image));
+    if let Some(dedicated_info) = &mut dedicated_info {
+        alloc_info = alloc_info.push_next(dedicated_info);
+    }
+    let mem = unsafe { vk_dev.allocate_memory(&alloc_info, None) }?;
+    _ = fd.into_raw_fd();
+    Ok(mem)
+}
+
+fn mutable_format_flags(view_formats: &[vk::Format]) -> vk::ImageCreateFlags {
+    if view_formats.is_empty() {
+        vk::ImageCreateFlags::empty()
+    } else {
+        vk::ImageCreateFlags::MUTABLE_FORMAT
+    }
+}
+
+/// Checks that an image with this format, modifier and usage can be imported from a dmabuf and
+/// is large enough for `extent`.
+unsafe fn check_image_format_support(
+    instance: &ash::Instance,
+    phys_dev: vk::PhysicalDevice,
+    format: vk::Format,
+    view_formats: &[vk::Format],
+    modifier: u64,
+    usage: vk::ImageUsageFlags,
+    extent: vk::Extent3D,
+) -> Result<(), ImportError> {
+    let (props, external_props) = match unsafe {
+        get_image_format_properties(instance, phys_dev, format, view_formats, modifier, usage)
+    } {
+        Ok(props) => props,
+        Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED) => {
+            return Err(ImportError::ModifierInvalid(Modifier(modifier)));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if !external_props
+        .external_memory_features
+        .contains(vk::ExternalMemoryFeatureFlags::IMPORTABLE)
+    {
+        return Err(ImportError::ModifierInvalid(Modifier(modifier)));
+    }
+    if extent.width > props.max_extent.width || extent.height > props.max_extent.height {
+        return Err(ImportError::ExtentTooLarge);
+    }
+    Ok(())
+}
+
+/// Queries the image format properties of a dmabuf backed image with this format, modifier and
+/// usage. `view_formats` lists the formats the image can be viewed as, if there are any besides
+/// `format`.
+pub(crate) unsafe fn get_image_format_properties(
+    instance: &ash::Instance,
+    phys_dev: vk::PhysicalDevice,
+    format: vk::Format,
+    view_formats: &[vk::Format],
+    modifier: u64,
+    usage: vk::ImageUsageFlags,
+) -> Result<(vk::ImageFormatProperties, vk::ExternalMemoryProperties), vk::Result> {
+    let mut modifier_info = vk::PhysicalDeviceImageDrmFormatModifierInfoEXT::default()
+        .drm_format_modifier(modifier)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE);
+    let mut external_info = vk::PhysicalDeviceExternalImageFormatInfo::default()
+        .handle_type(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT);
+    let mut format_list = vk::ImageFormatListCreateInfo::default().view_formats(view_formats);
+    let format_info = vk::PhysicalDeviceImageFormatInfo2::default()
+        .flags(mutable_format_flags(view_formats))
+        .format(format)
+        .ty(vk::ImageType::TYPE_2D)
+        .tiling(vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT)
+        .usage(usage)
+        .push_next(&mut modifier_info)
+        .push_next(&mut external_info)
+        .push_next(&mut format_list);
+    let mut external_props = vk::ExternalImageFormatProperties::default();
+    let mut props = vk::ImageFormatProperties2::default().push_next(&mut external_props);
+    unsafe {
+        instance.get_physical_device_image_format_properties2(phys_dev, &format_info, &mut props)
+    }?;
+    let image_format_properties = props.image_format_properties;
+    Ok((
+        image_format_properties,
+        external_props.external_memory_properties,
+    ))
+}
@@ -1,16 +1,11 @@
-// pub mod export;
+pub mod capabilities;
+mod convert;
 pub mod dmatex;
+pub mod export;
+pub mod feedback;
 pub mod format_mapping;
 pub mod import;
-
-// TODO: this NEEDS to be implemented somehow
-
-// pub fn required_device_extensions() -> Vec<&'static CStr> {
-//     vec![
-//         ash::ext::image_drm_format_modifier::NAME,
-//         ash::ext::external_memory_dma_buf::NAME,
-//         ash::khr::external_memory_fd::NAME,
-//         ash::khr::external_memory::NAME,
-//         ash::khr::swapchain::NAME,
-//     ]
-// }
+pub mod modifier;
+pub mod stream;
+mod sync;
+pub mod wgpu_init;
@@ -0,0 +1,373 @@
+use std::fmt::{self, Debug, Display, Write as _};
+
+pub const DRM_FORMAT_MOD_LINEAR: u64 = 0;
+pub const DRM_FORMAT_MOD_INVALID: u64 = 0x00ff_ffff_ffff_ffff;
+
+/// The vendor stored in the top 8 bits of a DRM format modifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ModifierVendor {
+    None,
+    Intel,
+    Amd,
+    Nvidia,
+    Samsung,
+    Qcom,
+    Vivante,
+    Broadcom,
+    Arm,
+    Allwinner,
+    Amlogic,
+    Mediatek,
+    Apple,
+    Unknown(u8),
+}
+
+impl ModifierVendor {
+    fn from_code(code: u8) -> Self {
+        match code {
+            0x00 => Self::None,
+            0x01 => Self::Intel,
+            0x02 => Self::Amd,
+            0x03 => Self::Nvidia,
+            0x04 => Self::Samsung,
+            0x05 => Self::Qcom,
+            0x06 => Self::Vivante,
+            0x07 => Self::Broadcom,
+            0x08 => Self::Arm,
+            0x09 => Self::Allwinner,
+            0x0a => Self::Amlogic,
+            0x0b => Self::Mediatek,
+            0x0c => Self::Apple,
+            code => Self::Unknown(code),
+        }
+    }
+
+    /// The `DRM_FORMAT_MOD_VENDOR_*` suffix.
+    fn name(self) -> Option<&'static str> {
+        Some(match self {
+            Self::None => "NONE",
+            Self::Intel => "INTEL",
+            Self::Amd => "AMD",
+            Self::Nvidia => "NVIDIA",
+            Self::Samsung => "SAMSUNG",
+            Self::Qcom => "QCOM",
+            Self::Vivante => "VIVANTE",
+            Self::Broadcom => "BROADCOM",
+            Self::Arm => "ARM",
+            Self::Allwinner => "ALLWINNER",
+            Self::Amlogic => "AMLOGIC",
+            Self::Mediatek => "MTK",
+            Self::Apple => "APPLE",
+            Self::Unknown(_) => return None,
+        })
+    }
+}
+
+/// A DRM format modifier, displayed with the name `drm_fourcc.h` gives it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Modifier(pub u64);
+
+impl Modifier {
+    pub fn vendor(self) -> ModifierVendor {
+        ModifierVendor::from_code((self.0 >> 56) as u8)
+    }
+
+    /// The vendor specific part of the modifier.
+    pub fn value(self) -> u64 {
+        self.0 & 0x00ff_ffff_ffff_ffff
+    }
+
+    pub fn is_linear(self) -> bool {
+        self.0 == DRM_FORMAT_MOD_LINEAR
+    }
+
+    pub fn is_invalid(self) -> bool {
+        self.0 == DRM_FORMAT_MOD_INVALID
+    }
+
+    /// Whether buffers with this modifier carry an extra plane of compression metadata, like the
+    /// CCS plane of Intel or the DCC plane of AMD.
+    pub fn has_aux_plane(self) -> bool {
+        match self.vendor() {
+            // Y_CCS, Yf_CCS, the GEN12 and the MTL CCS modifiers, DG2 and later use flat CCS
+            ModifierVendor::Intel => matches!(self.value(), 4..=8 | 13..=15),
+            ModifierVendor::Amd => self.amd_field(AMD_FMT_MOD_DCC) != 0,
+            _ => false,
+        }
+    }
+
+    /// The name of the modifier as spelled in `drm_fourcc.h`, parameterized modifiers are written
+    /// as the macro invocation that creates them.
+    pub fn name(self) -> String {
+        if self.is_linear() {
+            return "DRM_FORMAT_MOD_LINEAR".into();
+        }
+        if self.is_invalid() {
+            return "DRM_FORMAT_MOD_INVALID".into();
+        }
+        let value = self.value();
+        let name = match self.vendor() {
+            ModifierVendor::Intel => intel_name(value).map(String::from),
+            ModifierVendor::Amd => Some(self.amd_name()),
+            ModifierVendor::Nvidia => nvidia_name(value),
+            ModifierVendor::Arm => arm_name(value),
+            ModifierVendor::Broadcom => broadcom_name(value),
+            ModifierVendor::Samsung => match value {
+                1 => Some("DRM_FORMAT_MOD_SAMSUNG_64_32_TILE".into()),
+                2 => Some("DRM_FORMAT_MOD_SAMSUNG_16_16_TILE".into()),
+                _ => None,
+            },
+            ModifierVendor::Qcom => match value {
+                1 => Some("DRM_FORMAT_MOD_QCOM_COMPRESSED".into()),
+                2 => Some("DRM_FORMAT_MOD_QCOM_TILED2".into()),
+                3 => Some("DRM_FORMAT_MOD_QCOM_TILED3".into()),
+                _ => None,
+            },
+            ModifierVendor::Vivante => match value {
+                1 => Some("DRM_FORMAT_MOD_VIVANTE_TILED".into()),
+                2 => Some("DRM_FORMAT_MOD_VIVANTE_SUPER_TILED".into()),
+                3 => Some("DRM_FORMAT_MOD_VIVANTE_SPLIT_TILED".into()),
+                4 => Some("DRM_FORMAT_MOD_VIVANTE_SPLIT_SUPER_TILED".into()),
+                _ => None,
+            },
+            ModifierVendor::Allwinner => match value {
+                1 => Some("DRM_FORMAT_MOD_ALLWINNER_TILED".into()),
+                _ => None,
+            },
+            _ => None,
+        };
+        name.unwrap_or_else(|| match self.vendor().name() {
+            Some(vendor) => format!("fourcc_mod_code({vendor}, {value:#x})"),
+            None => format!("{:#018x}", self.0),
+        })
+    }
+
+    fn amd_field(self, (shift, mask): (u32, u64)) -> u64 {
+        (self.0 >> shift) & mask
+    }
+
+    /// Written as the `AMD_FMT_MOD_SET` expression building the modifier.
+    fn amd_name(self) -> String {
+        let tile_version = match self.amd_field(AMD_FMT_MOD_TILE_VERSION) {
+            1 => "AMD_FMT_MOD_TILE_VER_GFX9".into(),
+            2 => "AMD_FMT_MOD_TILE_VER_GFX10".into(),
+            3 => "AMD_FMT_MOD_TILE_VER_GFX10_RBPLUS".into(),
+            4 => "AMD_FMT_MOD_TILE_VER_GFX11".into(),
+            5 => "AMD_FMT_MOD_TILE_VER_GFX12".into(),
+            version => version.to_string(),
+        };
+        let tile = match (
+            self.amd_field(AMD_FMT_MOD_TILE_VERSION),
+            self.amd_field(AMD_FMT_MOD_TILE),
+        ) {
+            (5, 1) => "AMD_FMT_MOD_TILE_GFX12_256B_2D".into(),
+            (5, 2) => "AMD_FMT_MOD_TILE_GFX12_4K_2D".into(),
+            (5, 3) => "AMD_FMT_MOD_TILE_GFX12_64K_2D".into(),
+            (5, 4) => "AMD_FMT_MOD_TILE_GFX12_256K_2D".into(),
+            (5, tile) => tile.to_string(),
+            (_, 9) => "AMD_FMT_MOD_TILE_GFX9_64K_S".into(),
+            (_, 10) => "AMD_FMT_MOD_TILE_GFX9_64K_D".into(),
+            (_, 25) => "AMD_FMT_MOD_TILE_GFX9_64K_S_X".into(),
+            (_, 26) => "AMD_FMT_MOD_TILE_GFX9_64K_D_X".into(),
+            (_, 27) => "AMD_FMT_MOD_TILE_GFX9_64K_R_X".into(),
+            (_, 31) => "AMD_FMT_MOD_TILE_GFX11_256K_R_X".into(),
+            (_, tile) => tile.to_string(),
+        };
+        let mut name = format!("AMD_FMT_MOD | AMD_FMT_MOD_SET(TILE_VERSION, {tile_version})");
+        _ = write!(name, " | AMD_FMT_MOD_SET(TILE, {tile})");
+        for (field_name, field) in [
+            ("DCC", AMD_FMT_MOD_DCC),
+            ("DCC_RETILE", AMD_FMT_MOD_DCC_RETILE),
+            ("DCC_PIPE_ALIGN", AMD_FMT_MOD_DCC_PIPE_ALIGN),
+            ("DCC_INDEPENDENT_64B", AMD_FMT_MOD_DCC_INDEPENDENT_64B),
+            ("DCC_INDEPENDENT_128B", AMD_FMT_MOD_DCC_INDEPENDENT_128B),
+            (
+                "DCC_MAX_COMPRESSED_BLOCK",
+                AMD_FMT_MOD_DCC_MAX_COMPRESSED_BLOCK,
+            ),
+            ("DCC_CONSTANT_ENCODE", AMD_FMT_MOD_DCC_CONSTANT_ENCODE),
+            ("PIPE_XOR_BITS", AMD_FMT_MOD_PIPE_XOR_BITS),
+            ("BANK_XOR_BITS", AMD_FMT_MOD_BANK_XOR_BITS),
+            ("PACKERS", AMD_FMT_MOD_PACKERS),
+            ("RB", AMD_FMT_MOD_RB),
+            ("PIPE", AMD_FMT_MOD_PIPE),
+        ] {
+            let value = self.amd_field(field);
+            if value != 0 {
+                _ = write!(name, " | AMD_FMT_MOD_SET({field_name}, {value})");
+            }
+        }
+        name
+    }
+}
+
+impl From<u64> for Modifier {
+    fn from(modifier: u64) -> Self {
+        Self(modifier)
+    }
+}
+
+impl Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+impl Debug for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:#x})", self.name(), self.0)
+    }
+}
+
+// (shift, mask) of the AMD_FMT_MOD fields, named like the `*_SHIFT` and `*_MASK` pairs of
+// drm_fourcc.h
+const AMD_FMT_MOD_TILE_VERSION: (u32, u64) = (0, 0xff);
+const AMD_FMT_MOD_TILE: (u32, u64) = (8, 0x1f);
+const AMD_FMT_MOD_DCC: (u32, u64) = (13, 0x1);
+const AMD_FMT_MOD_DCC_RETILE: (u32, u64) = (14, 0x1);
+const AMD_FMT_MOD_DCC_PIPE_ALIGN: (u32, u64) = (15, 0x1);
+const AMD_FMT_MOD_DCC_INDEPENDENT_64B: (u32, u64) = (16, 0x1);
+const AMD_FMT_MOD_DCC_INDEPENDENT_128B: (u32, u64) = (17, 0x1);
+const AMD_FMT_MOD_DCC_MAX_COMPRESSED_BLOCK: (u32, u64) = (18, 0x3);
+const AMD_FMT_MOD_DCC_CONSTANT_ENCODE: (u32, u64) = (20, 0x1);
+const AMD_FMT_MOD_PIPE_XOR_BITS: (u32, u64) = (21, 0x7);
+const AMD_FMT_MOD_BANK_XOR_BITS: (u32, u64) = (24, 0x7);
+const AMD_FMT_MOD_PACKERS: (u32, u64) = (27, 0x7);
+const AMD_FMT_MOD_RB: (u32, u64) = (30, 0x7);
+const AMD_FMT_MOD_PIPE: (u32, u64) = (33, 0x7);
+
+fn intel_name(value: u64) -> Option<&'static str> {
+    Some(match value {
+        1 => "I915_FORMAT_MOD_X_TILED",
+        2 => "I915_FORMAT_MOD_Y_TILED",
+        3 => "I915_FORMAT_MOD_Yf_TILED",
+        4 => "I915_FORMAT_MOD_Y_TILED_CCS",
+        5 => "I915_FORMAT_MOD_Yf_TILED_CCS",
+        6 => "I915_FORMAT_MOD_Y_TILED_GEN12_RC_CCS",
+        7 => "I915_FORMAT_MOD_Y_TILED_GEN12_MC_CCS",
+        8 => "I915_FORMAT_MOD_Y_TILED_GEN12_RC_CCS_CC",
+        9 => "I915_FORMAT_MOD_4_TILED",
+        10 => "I915_FORMAT_MOD_4_TILED_DG2_RC_CCS",
+        11 => "I915_FORMAT_MOD_4_TILED_DG2_MC_CCS",
+        12 => "I915_FORMAT_MOD_4_TILED_DG2_RC_CCS_CC",
+        13 => "I915_FORMAT_MOD_4_TILED_MTL_RC_CCS",
+        14 => "I915_FORMAT_MOD_4_TILED_MTL_MC_CCS",
+        15 => "I915_FORMAT_MOD_4_TILED_MTL_RC_CCS_CC",
+        16 => "I915_FORMAT_MOD_4_TILED_LNL_CCS",
+        17 => "I915_FORMAT_MOD_4_TILED_BMG_CCS",
+        _ => return None,
+    })
+}
+
+fn nvidia_name(value: u64) -> Option<String> {
+    if value == 1 {
+        return Some("DRM_FORMAT_MOD_NVIDIA_TEGRA_TILED".into());
+    }
+    if value & 0x10 == 0 || value >> 26 != 0 {
+        return None;
+    }
+    let h = value & 0xf;
+    let k = (value >> 12) & 0xff;
+    let g = (value >> 20) & 0x3;
+    let s = (value >> 22) & 0x1;
+    let c = (value >> 23) & 0x7;
+    Some(if value & !0x1f == 0 && h <= 5 {
+        format!("DRM_FORMAT_MOD_NVIDIA_16BX2_BLOCK({h})")
+    } else {
+        format!("DRM_FORMAT_MOD_NVIDIA_BLOCK_LINEAR_2D({c}, {s}, {g}, {k:#x}, {h})")
+    })
+}
+
+fn arm_name(value: u64) -> Option<String> {
+    let kind = value >> 52;
+    let value = value & 0x000f_ffff_ffff_ffff;
+    match kind {
+        // AFBC
+        0x00 => {
+            let block_size = match value & 0xf {
+                1 => "AFBC_FORMAT_MOD_BLOCK_SIZE_16x16",
+                2 => "AFBC_FORMAT_MOD_BLOCK_SIZE_32x8",
+                3 => "AFBC_FORMAT_MOD_BLOCK_SIZE_64x4",
+                4 => "AFBC_FORMAT_MOD_BLOCK_SIZE_32x8_64x4",
+                _ => return None,
+            };
+            let mut flags = vec![block_size];
+            for (bit, flag) in [
+                (4, "AFBC_FORMAT_MOD_YTR"),
+                (5, "AFBC_FORMAT_MOD_SPLIT"),
+                (6, "AFBC_FORMAT_MOD_SPARSE"),
+                (7, "AFBC_FORMAT_MOD_CBR"),
+                (8, "AFBC_FORMAT_MOD_TILED"),
+                (9, "AFBC_FORMAT_MOD_SC"),
+                (10, "AFBC_FORMAT_MOD_DB"),
+                (11, "AFBC_FORMAT_MOD_BCH"),
+                (12, "AFBC_FORMAT_MOD_USM"),
+            ] {
+                if value & (1 << bit) != 0 {
+                    flags.push(flag);
+                }
+            }
+            Some(format!("DRM_FORMAT_MOD_ARM_AFBC({})", flags.join(" | ")))
+        }
+        // MISC
+        0x01 if value == 1 => Some("DRM_FORMAT_MOD_ARM_16X16_BLOCK_U_INTERLEAVED".into()),
+        // AFRC
+        0x02 => Some(format!("DRM_FORMAT_MOD_ARM_AFRC({value:#x})")),
+        _ => None,
+    }
+}
+
+fn broadcom_name(value: u64) -> Option<String> {
+    let param = value >> 8;
+    let sand = |size: u32| {
+        Some(match param {
+            0 => format!("DRM_FORMAT_MOD_BROADCOM_SAND{size}"),
+            height => format!("DRM_FORMAT_MOD_BROADCOM_SAND{size}_COL_HEIGHT({height})"),
+        })
+    };
+    match value & 0xff {
+        1 if param == 0 => Some("DRM_FORMAT_MOD_BROADCOM_VC4_T_TILED".into()),
+        2 => sand(32),
+        3 => sand(64),
+        4 => sand(128),
+        5 => sand(256),
+        6 if param == 0 => Some("DRM_FORMAT_MOD_BROADCOM_UIF".into()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use drm_fourcc::DrmModifier;
+
+    #[test]
+    fn names() {
+        for (modifier, name) in [
+            (DrmModifier::Linear, "DRM_FORMAT_MOD_LINEAR"),
+            (DrmModifier::Invalid, "DRM_FORMAT_MOD_INVALID"),
+            (DrmModifier::I915_y_tiled_ccs, "I915_FORMAT_MOD_Y_TILED_CCS"),
+            (
+                DrmModifier::Nvidia_16bx2_block_four_gob,
+                "DRM_FORMAT_MOD_NVIDIA_16BX2_BLOCK(2)",
+            ),
+            (
+                DrmModifier::Broadcom_sand128,
+                "DRM_FORMAT_MOD_BROADCOM_SAND128",
+            ),
+        ] {
+            assert_eq!(Modifier(modifier.into()).name(), name);
+        }
+        // GFX10 64K_R_X with DCC and 3 pipe xor bits
+        let amd = (2 << 56) | 2 | (27 << 8) | (1 << 13) | (3 << 21);
+        assert_eq!(
+            Modifier(amd).name(),
+            "AMD_FMT_MOD | AMD_FMT_MOD_SET(TILE_VERSION, AMD_FMT_MOD_TILE_VER_GFX10) \
+             | AMD_FMT_MOD_SET(TILE, AMD_FMT_MOD_TILE_GFX9_64K_R_X) | AMD_FMT_MOD_SET(DCC, 1) \
+             | AMD_FMT_MOD_SET(PIPE_XOR_BITS, 3)"
+        );
+        assert!(Modifier(amd).has_aux_plane());
+        assert!(Modifier(DrmModifier::I915_y_tiled_gen12_rc_ccs.into()).has_aux_plane());
+        assert!(!Modifier(DrmModifier::I915_y_tiled.into()).has_aux_plane());
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+
+use bevy::{asset::Handle, ecs::component::Component, image::Image};
+
+use crate::{
+    dmatex::Dmatex,
+    format_mapping::fourcc_has_alpha,
+    import::{DmatexRelease, DmatexUsage, DropCallback, ImportedTexture},
+};
+
+/// What happens to frames that are submitted to a [`DmabufStream`] faster than they are rendered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DmabufStreamMode {
+    /// Only the latest frame is shown, frames replaced before being shown are dropped right away.
+    #[default]
+    Mailbox,
+    /// Every frame is shown, one per rendered frame.
+    Fifo,
+}
+
+/// An image showing the latest frame submitted with
+/// [`ImportedDmatexs::submit`](crate::import::ImportedDmatexs::submit). The handle never changes,
+/// so materials, sprites and UI don't have to be re-pointed for new frames.
+/// Created with [`ImportedDmatexs::create_stream`](crate::import::ImportedDmatexs::create_stream),
+/// the stream ends once every clone of its handle is dropped.
+#[derive(Component, Clone, Debug)]
+pub struct DmabufStream {
+    pub(crate) handle: Handle<Image>,
+    pub(crate) mode: DmabufStreamMode,
+}
+
+impl DmabufStream {
+    pub fn handle(&self) -> &Handle<Image> {
+        &self.handle
+    }
+    pub fn mode(&self) -> DmabufStreamMode {
+        self.mode
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct StreamFrame {
+    pub(crate) buf: Dmatex,
+    pub(crate) on_drop: DropCallback,
+    pub(crate) usage: DmatexUsage,
+    pub(crate) release: DmatexRelease,
+}
+
+/// The frames of a [`DmabufStream`], shared with the render world.
+#[derive(Debug)]
+pub(crate) struct StreamState {
+    mode: DmabufStreamMode,
+    pending: VecDeque<StreamFrame>,
+    /// the import currently shown
+    pub(crate) current: Option<ImportedTexture>,
+}
+
+impl StreamState {
+    pub(crate) fn new(mode: DmabufStreamMode) -> Self {
+        Self {
+            mode,
+            pending: VecDeque::new(),
+            current: None,
+        }
+    }
+    pub(crate) fn push(&mut self, frame: StreamFrame) {
+        if self.mode == DmabufStreamMode::Mailbox {
+            self.pending.clear();
+        }
+        self.pending.push_back(frame);
+    }
+    /// The frame to show next, if a new one was submitted.
+    pub(crate) fn next(&mut self) -> Option<StreamFrame> {
+        self.pending.pop_front()
+    }
+    /// Whether the alpha channel of the latest frame is meaningful.
+    pub(crate) fn has_alpha(&self) -> Option<bool> {
+        match self.pending.back() {
+            Some(frame) => Some(fourcc_has_alpha(frame.buf.format)),
+            None => self.current.as_ref().map(ImportedTexture::has_alpha),
+        }
+    }
+}
@@ -0,0 +1,582 @@
+use std::{
+    fmt::Debug,
+    io,
+    os::fd::{AsFd as _, AsRawFd as _, BorrowedFd, FromRawFd as _, IntoRawFd as _, OwnedFd},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use ash::{ext, khr, vk};
+use bevy::ecs::resource::Resource;
+use wgpu::hal::vulkan::Api as Vulkan;
+
+use crate::{
+    dmatex::{DmatexExplicitSync, SyncobjPoint},
+    import::ImportError,
+};
+
+/// The acquire and release points of a dmatex, imported as Vulkan timeline semaphores.
+pub(crate) struct ExplicitSync {
+    device: ash::Device,
+    acquire: TimelinePoint,
+    release: TimelinePoint,
+    acquired: AtomicBool,
+}
+
+#[derive(Debug)]
+struct TimelinePoint {
+    semaphore: vk::Semaphore,
+    value: u64,
+}
+
+impl Debug for ExplicitSync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExplicitSync")
+            .field("acquire", &self.acquire)
+            .field("release", &self.release)
+            .field("acquired", &self.acquired)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExplicitSync {
+    /// Imports the syncobjs of `sync` into timeline semaphores on `dev`.
+    pub(crate) unsafe fn import(
+        dev: &wgpu::hal::vulkan::Device,
+        sync: &DmatexExplicitSync,
+    ) -> Result<Self, ImportError> {
+        unsafe { check_explicit_sync_support(dev) }?;
+        let acquire = unsafe { import_timeline_point(dev, &sync.acquire) }?;
+        let release = match unsafe { import_timeline_point(dev, &sync.release) } {
+            Ok(release) => release,
+            Err(err) => {
+                unsafe { dev.raw_device().destroy_semaphore(acquire.semaphore, None) };
+                return Err(err);
+            }
+        };
+        Ok(Self {
+            device: dev.raw_device().clone(),
+            acquire,
+            release,
+            acquired: AtomicBool::new(false),
+        })
+    }
+
+    /// Blocks until the acquire point is reached, for at most `timeout`. Returns whether it was
+    /// reached, it's only waited for once.
+    pub(crate) fn wait_acquire(&self, timeout: Duration) -> Result<bool, vk::Result> {
+        if self.acquired.load(Ordering::Relaxed) {
+            return Ok(true);
+        }
+        let semaphores = [self.acquire.semaphore];
+        let values = [self.acquire.value];
+        let wait_info = vk::SemaphoreWaitInfo::default()
+            .semaphores(&semaphores)
+            .values(&values);
+        let timeout = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+        match unsafe { self.device.wait_semaphores(&wait_info, timeout) } {
+            Ok(()) => {
+                self.acquired.store(true, Ordering::Relaxed);
+                Ok(true)
+            }
+            Err(vk::Result::TIMEOUT) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Signals the release point from the host, once nothing reads the dmatex anymore. Returns
+    /// false without signaling while the acquire point hasn't been reached, the release point may
+    /// never be signaled before it, even if the dmatex was never rendered.
+    pub(crate) fn signal_release(&self) -> Result<bool, vk::Result> {
+        let counter = |point: &TimelinePoint| unsafe {
+            self.device.get_semaphore_counter_value(point.semaphore)
+        };
+        if counter(&self.acquire)? < self.acquire.value {
+            return Ok(false);
+        }
+        // signaling a value the timeline already passed is invalid
+        if counter(&self.release)? < self.release.value {
+            unsafe {
+                self.device.signal_semaphore(
+                    &vk::SemaphoreSignalInfo::default()
+                        .semaphore(self.release.semaphore)
+                        .value(self.release.value),
+                )
+            }?;
+        }
+        Ok(true)
+    }
+}
+
+impl Drop for ExplicitSync {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_semaphore(self.acquire.semaphore, None);
+            self.device.destroy_semaphore(self.release.semaphore, None);
+        }
+    }
+}
+
+/// Checks that `dev` can import syncobjs as timeline semaphores and query their value.
+unsafe fn check_explicit_sync_support(dev: &wgpu::hal::vulkan::Device) -> Result<(), ImportError> {
+    if !dev
+        .enabled_device_extensions()
+        .contains(&khr::external_semaphore_fd::NAME)
+    {
+        return Err(ImportError::ExplicitSyncUnsupported);
+    }
+    let instance = dev.shared_instance().raw_instance();
+    let props = unsafe { instance.get_physical_device_properties(dev.raw_physical_device()) };
+    // vkGetSemaphoreCounterValue, vkWaitSemaphores and vkSignalSemaphore are only loaded from the
+    // core 1.2 entry points
+    if props.api_version < vk::API_VERSION_1_2 {
+        return Err(ImportError::ExplicitSyncUnsupported);
+    }
+    let mut type_info =
+        vk::SemaphoreTypeCreateInfo::default().semaphore_type(vk::SemaphoreType::TIMELINE);
+    let info = vk::PhysicalDeviceExternalSemaphoreInfo::default()
+        .handle_type(vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_FD)
+        .push_next(&mut type_info);
+    let mut external_props = vk::ExternalSemaphoreProperties::default();
+    unsafe {
+        instance.get_physical_device_external_semaphore_properties(
+            dev.raw_physical_device(),
+            &info,
+            &mut external_props,
+        )
+    };
+    if !external_props
+        .external_semaphore_features
+        .contains(vk::ExternalSemaphoreFeatureFlags::IMPORTABLE)
+    {
+        return Err(ImportError::ExplicitSyncUnsupported);
+    }
+    Ok(())
+}
+
+/// Creates a timeline semaphore sharing its payload with the syncobj of `point`.
+unsafe fn import_timeline_point(
+    dev: &wgpu::hal::vulkan::Device,
+    point: &SyncobjPoint,
+) -> Result<TimelinePoint, ImportError> {
+    let instance = dev.shared_instance().raw_instance();
+    let vk_dev = dev.raw_device();
+    let semaphore_fd = khr::external_semaphore_fd::Device::new(instance, vk_dev);
+
+    let mut type_info = vk::SemaphoreTypeCreateInfo::default()
+        .semaphore_type(vk::SemaphoreType::TIMELINE)
+        .initial_value(0);
+    let semaphore = unsafe {
+        vk_dev.create_semaphore(
+            &vk::SemaphoreCreateInfo::default().push_next(&mut type_info),
+            None,
+        )
+    }?;
+
+    // on success vulkan takes ownership of the fd, so hand it a duplicate
+    let fd = match point.syncobj_fd.as_fd().try_clone_to_owned() {
+        Ok(fd) => fd,
+        Err(err) => {
+            unsafe { vk_dev.destroy_semaphore(semaphore, None) };
+            return Err(ImportError::DuplicateSyncobjFd(err));
+        }
+    };
+    let import_info = vk::ImportSemaphoreFdInfoKHR::default()
+        .semaphore(semaphore)
+        .handle_type(vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_FD)
+        .fd(fd.as_raw_fd());
+    if let Err(err) = unsafe { semaphore_fd.import_semaphore_fd(&import_info) } {
+        unsafe { vk_dev.destroy_semaphore(semaphore, None) };
+        return Err(err.into());
+    }
+    _ = fd.into_raw_fd();
+    Ok(TimelinePoint {
+        semaphore,
+        value: point.point,
+    })
+}
+
+/// `struct dma_buf_export_sync_file` from linux/dma-buf.h
+#[repr(C)]
+struct DmaBufSyncFile {
+    flags: u32,
+    fd: i32,
+}
+
+const DMA_BUF_SYNC_READ: u32 = 1 << 0;
+/// `_IOWR('b', 2, struct dma_buf_export_sync_file)`
+const DMA_BUF_IOCTL_EXPORT_SYNC_FILE: u64 = 0xc008_6202;
+
+/// Exports the fences a reader of `dmabuf` has to wait for as a sync_file.
+fn export_sync_file(dmabuf: BorrowedFd) -> io::Result<OwnedFd> {
+    let mut arg = DmaBufSyncFile {
+        flags: DMA_BUF_SYNC_READ,
+        fd: -1,
+    };
+    let ret = unsafe {
+        libc::ioctl(
+            dmabuf.as_raw_fd(),
+            DMA_BUF_IOCTL_EXPORT_SYNC_FILE as _,
+            &mut arg,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(arg.fd) })
+}
+
+/// Waits on the host for the implicit sync of dmabufs, through sync_files.
+#[derive(Resource, Default)]
+pub(crate) struct ImplicitSync;
+
+impl ImplicitSync {
+    /// Blocks until the pending writes to `dmabufs` are done, for at most `timeout` in total.
+    /// Returns whether they are.
+    pub(crate) fn wait_for_writes<'a>(
+        &self,
+        dmabufs: impl IntoIterator<Item = BorrowedFd<'a>>,
+        timeout: Duration,
+    ) -> io::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        for dmabuf in dmabufs {
+            let sync_file = export_sync_file(dmabuf)?;
+            // a sync_file becomes readable once its fence signaled
+            let mut poll_fd = libc::pollfd {
+                fd: sync_file.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let timeout = i32::try_from(remaining.as_millis()).unwrap_or(i32::MAX);
+                match unsafe { libc::poll(&mut poll_fd, 1, timeout) } {
+                    0 => return Ok(false),
+                    1.. => break,
+                    _ => {
+                        let err = io::Error::last_os_error();
+                        if err.kind() != io::ErrorKind::Interrupted {
+                            return Err(err);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Direction of a queue family ownership transfer of imported images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum OwnershipTransfer {
+    /// from the foreign queue family to bevy's
+    Acquire,
+    /// from bevy's queue family back to the foreign one
+    Release,
+}
+
+/// Records the queue family ownership transfers of imported images into wgpu command encoders, so
+/// they are submitted through wgpu's queue.
+///
+/// wgpu tracks the images as used for `COPY_SRC` while the foreign queue family owns them.
+/// Acquired images are transitioned from `GENERAL` to `TRANSFER_SRC_OPTIMAL`, and released ones
+/// have to be copied from by wgpu first, which transitions them back to that layout.
+#[derive(Resource)]
+pub(crate) struct OwnershipTransfers {
+    device: ash::Device,
+    queue_family: u32,
+    foreign_queue_family: u32,
+}
+
+impl OwnershipTransfers {
+    pub(crate) fn new(dev: &wgpu::hal::vulkan::Device) -> Self {
+        let foreign_queue_family = if dev
+            .enabled_device_extensions()
+            .contains(&ext::queue_family_foreign::NAME)
+        {
+            vk::QUEUE_FAMILY_FOREIGN_EXT
+        } else {
+            vk::QUEUE_FAMILY_EXTERNAL
+        };
+        Self {
+            device: dev.raw_device().clone(),
+            queue_family: dev.queue_family_index(),
+            foreign_queue_family,
+        }
+    }
+
+    /// Records the ownership transfer of `images`.
+    ///
+    /// # Safety
+    /// The images have to be created on the device this was created with, and be in the layout
+    /// described above.
+    pub(crate) unsafe fn record(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        images: &[vk::Image],
+        transfer: OwnershipTransfer,
+    ) {
+        let barriers = images
+            .iter()
+            .map(|image| {
+                let barrier = color_barrier(*image);
+                match transfer {
+                    OwnershipTransfer::Acquire => barrier
+                        .src_access_mask(vk::AccessFlags::NONE)
+                        .dst_access_mask(
+                            vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                        )
+                        .old_layout(vk::ImageLayout::GENERAL)
+                        .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                        .src_queue_family_index(self.foreign_queue_family)
+                        .dst_queue_family_index(self.queue_family),
+                    OwnershipTransfer::Release => barrier
+                        .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+                        .dst_access_mask(vk::AccessFlags::NONE)
+                        .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                        .new_layout(vk::ImageLayout::GENERAL)
+                        .src_queue_family_index(self.queue_family)
+                        .dst_queue_family_index(self.foreign_queue_family),
+                }
+            })
+            .collect::<Vec<_>>();
+        unsafe {
+            self.record_raw(encoder, |command_buffer| {
+                self.device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::ALL_COMMANDS,
+                    vk::PipelineStageFlags::ALL_COMMANDS,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &barriers,
+                );
+            })
+        };
+    }
+
+    /// Records copying the images of `backup` into its buffer, right after they were acquired.
+    ///
+    /// # Safety
+    /// `backup` has to be created on the device this was created with.
+    pub(crate) unsafe fn record_backup(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        backup: &ContentsBackup,
+    ) {
+        unsafe {
+            self.record_raw(encoder, |command_buffer| {
+                for (image, region) in &backup.regions {
+                    self.device.cmd_copy_image_to_buffer(
+                        command_buffer,
+                        *image,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        backup.buffer,
+                        std::slice::from_ref(region),
+                    );
+                }
+            })
+        };
+    }
+
+    /// Records copying the buffer of `backup` back into its images, once wgpu transitioned them
+    /// to `TRANSFER_SRC_OPTIMAL`. They are left in that layout.
+    ///
+    /// # Safety
+    /// `backup` has to be created on the device this was created with, and the backup has to be
+    /// completed.
+    pub(crate) unsafe fn record_restore(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        backup: &ContentsBackup,
+    ) {
+        let backup_written = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+        let to_dst = backup
+            .regions
+            .iter()
+            .map(|(image, _)| {
+                color_barrier(*image)
+                    .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                    .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            })
+            .collect::<Vec<_>>();
+        let to_src = backup
+            .regions
+            .iter()
+            .map(|(image, _)| {
+                color_barrier(*image)
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+                    .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            })
+            .collect::<Vec<_>>();
+        unsafe {
+            self.record_raw(encoder, |command_buffer| {
+                self.device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[backup_written],
+                    &[],
+                    &to_dst,
+                );
+                for (image, region) in &backup.regions {
+                    self.device.cmd_copy_buffer_to_image(
+                        command_buffer,
+                        backup.buffer,
+                        *image,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        std::slice::from_ref(region),
+                    );
+                }
+                self.device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::ALL_COMMANDS,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &to_src,
+                );
+            })
+        };
+    }
+
+    /// Runs `record` with the Vulkan command buffer of `encoder`, after the commands wgpu recorded
+    /// into it so far.
+    unsafe fn record_raw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        record: impl FnOnce(vk::CommandBuffer),
+    ) {
+        unsafe {
+            encoder.as_hal_mut::<Vulkan, _, _>(|encoder| {
+                if let Some(encoder) = encoder {
+                    record(encoder.raw_handle());
+                }
+            })
+        }
+    }
+}
+
+/// A barrier for the single color subresource of an imported image.
+fn color_barrier(image: vk::Image) -> vk::ImageMemoryBarrier<'static> {
+    vk::ImageMemoryBarrier::default()
+        .image(image)
+        .subresource_range(vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        })
+}
+
+/// A copy of the contents of acquired images wgpu hasn't used yet. wgpu transitions images from
+/// `UNDEFINED` on their first use, which may discard their contents (like the compression metadata
+/// of a modifier), so they are restored from this buffer afterwards.
+pub(crate) struct ContentsBackup {
+    device: ash::Device,
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    regions: Vec<(vk::Image, vk::BufferImageCopy)>,
+}
+
+impl ContentsBackup {
+    /// Creates a buffer holding `images`, given with their extent and bytes per texel.
+    ///
+    /// # Safety
+    /// The images have to be created on `dev`.
+    pub(crate) unsafe fn new(
+        dev: &wgpu::hal::vulkan::Device,
+        images: impl IntoIterator<Item = (vk::Image, vk::Extent2D, u32)>,
+    ) -> Result<Self, vk::Result> {
+        let instance = dev.shared_instance().raw_instance();
+        let vk_dev = dev.raw_device();
+
+        let mut size = 0;
+        let regions = images
+            .into_iter()
+            .map(|(image, extent, texel_size)| {
+                // a multiple of every texel size
+                let offset = u64::next_multiple_of(size, 16);
+                size = offset
+                    + u64::from(extent.width) * u64::from(extent.height) * u64::from(texel_size);
+                let region = vk::BufferImageCopy::default()
+                    .buffer_offset(offset)
+                    .image_subresource(vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    })
+                    .image_extent(extent.into());
+                (image, region)
+            })
+            .collect::<Vec<_>>();
+
+        let buffer = unsafe {
+            vk_dev.create_buffer(
+                &vk::BufferCreateInfo::default()
+                    .size(size.max(1))
+                    .usage(vk::BufferUsageFlags::TRANSFER_SRC | vk::BufferUsageFlags::TRANSFER_DST)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                None,
+            )
+        }?;
+        let requirements = unsafe { vk_dev.get_buffer_memory_requirements(buffer) };
+        let mem_properties =
+            unsafe { instance.get_physical_device_memory_properties(dev.raw_physical_device()) };
+        let memory_type_index = mem_properties
+            .memory_types_as_slice()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| requirements.memory_type_bits & (1 << i) != 0)
+            .min_by_key(|(_, mem_type)| {
+                !mem_type
+                    .property_flags
+                    .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            })
+            .map(|(i, _)| i as u32);
+        let result = memory_type_index
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .and_then(|memory_type_index| unsafe {
+                vk_dev.allocate_memory(
+                    &vk::MemoryAllocateInfo::default()
+                        .allocation_size(requirements.size)
+                        .memory_type_index(memory_type_index),
+                    None,
+                )
+            });
+        let memory = match result {
+            Ok(memory) => memory,
+            Err(err) => {
+                unsafe { vk_dev.destroy_buffer(buffer, None) };
+                return Err(err);
+            }
+        };
+        let backup = Self {
+            device: vk_dev.clone(),
+            buffer,
+            memory,
+            regions,
+        };
+        unsafe { vk_dev.bind_buffer_memory(buffer, memory, 0) }?;
+        Ok(backup)
+    }
+}
+
+impl Drop for ContentsBackup {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_buffer(self.buffer, None);
+            self.device.free_memory(self.memory, None);
+        }
+    }
+}
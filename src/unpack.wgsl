@@ -0,0 +1,43 @@
+// Unpacks a dmatex of a packed 16, 24 or 32 bit format, imported as raw integers, into an rgba texture.
+
+struct Params {
+    // bit offset of r, g, b and a in the little endian pixel
+    shifts: vec4<u32>,
+    // bit count of r, g, b and a, 0 for channels the format doesn't have
+    bits: vec4<u32>,
+    // 3 for pixels split into three r8uint texels, otherwise every pixel is a single texel
+    bytes_per_pixel: u32,
+    // source texel = transform * vec3(output texel, 1)
+    transform_x: vec4<i32>,
+    transform_y: vec4<i32>,
+}
+
+@group(0) @binding(0) var source: texture_2d<u32>;
+@group(0) @binding(1) var output: texture_storage_2d<OUTPUT_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+fn load_pixel(pos: vec2<u32>) -> u32 {
+    if params.bytes_per_pixel != 3u {
+        return textureLoad(source, pos, 0).r;
+    }
+    let x = pos.x * 3u;
+    return textureLoad(source, vec2(x, pos.y), 0).r
+        | (textureLoad(source, vec2(x + 1u, pos.y), 0).r << 8u)
+        | (textureLoad(source, vec2(x + 2u, pos.y), 0).r << 16u);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+    let pos = vec3(vec2<i32>(id.xy), 1);
+    let source_pos = vec2(dot(params.transform_x.xyz, pos), dot(params.transform_y.xyz, pos));
+    let pixel = load_pixel(vec2<u32>(source_pos));
+    let max = (vec4(1u) << params.bits) - 1u;
+    let channels = vec4<f32>((vec4(pixel) >> params.shifts) & max) / vec4<f32>(max);
+    // missing color channels are zero, missing alpha (like that of x formats) is opaque
+    let missing = vec4(0.0, 0.0, 0.0, 1.0);
+    textureStore(output, id.xy, select(channels, missing, params.bits == vec4(0u)));
+}
@@ -0,0 +1,214 @@
+use std::{ffi::CStr, sync::Arc};
+
+use ash::{ext, khr, vk};
+use bevy::{
+    app::{PluginGroup, PluginGroupBuilder},
+    render::{
+        RenderPlugin,
+        renderer::{
+            RenderAdapter, RenderAdapterInfo, RenderDevice, RenderInstance, RenderQueue,
+            WgpuWrapper,
+        },
+        settings::{RenderCreation, RenderResources, WgpuSettings, WgpuSettingsPriority},
+    },
+    tasks::block_on,
+    utils::default,
+};
+use thiserror::Error;
+use tracing::{error, info, warn};
+use wgpu::hal::{DeviceError, OpenDevice, vulkan::Api as Vulkan};
+
+/// Device extensions that have to be enabled to import dmabufs.
+pub fn required_device_extensions() -> Vec<&'static CStr> {
+    vec![
+        ext::image_drm_format_modifier::NAME,
+        ext::external_memory_dma_buf::NAME,
+        khr::external_memory_fd::NAME,
+        khr::external_memory::NAME,
+    ]
+}
+
+/// Device extensions that are enabled when the device supports them.
+pub fn optional_device_extensions() -> Vec<&'static CStr> {
+    vec![
+        ext::queue_family_foreign::NAME,
+        ext::physical_device_drm::NAME,
+        // importing DRM syncobjs for explicit sync
+        khr::external_semaphore_fd::NAME,
+        khr::external_semaphore::NAME,
+        // core since vulkan 1.2, but required by VK_EXT_image_drm_format_modifier
+        khr::image_format_list::NAME,
+    ]
+}
+
+/// Replaces the [`RenderCreation`] of the [`RenderPlugin`] in `plugins` with a Vulkan device that
+/// has the dmabuf extensions enabled, falling back to Bevy's default device creation on failure.
+pub fn add_dmabuf_init_plugin<G: PluginGroup>(plugins: G) -> PluginGroupBuilder {
+    let settings = WgpuSettings::default();
+    let render_creation = match create_render_resources(&settings) {
+        Ok(resources) => RenderCreation::Manual(resources),
+        Err(err) => {
+            error!(
+                "unable to create dmabuf capable render device, dmabuf importing will fail: {err}"
+            );
+            RenderCreation::Automatic(settings)
+        }
+    };
+    plugins.set(RenderPlugin {
+        render_creation,
+        ..default()
+    })
+}
+
+#[derive(Error, Debug)]
+pub enum InitError {
+    #[error("No Vulkan adapter found")]
+    NoAdapter,
+    #[error("Adapter is not a Vulkan Adapter")]
+    NotVulkan,
+    #[error("Adapter is missing required device extensions: {0:?}")]
+    MissingExtensions(Vec<&'static CStr>),
+    #[error("Vulkan Error: {0}")]
+    Vulkan(#[from] vk::Result),
+    #[error("Wgpu Device Error: {0}")]
+    Device(#[from] DeviceError),
+    #[error("Unable to request wgpu device: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+}
+
+/// Creates a wgpu Vulkan instance, adapter and device with the [`required_device_extensions`]
+/// (and all supported [`optional_device_extensions`]) enabled, ready to be passed to Bevy as
+/// [`RenderCreation::Manual`].
+pub fn create_render_resources(settings: &WgpuSettings) -> Result<RenderResources, InitError> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::VULKAN,
+        flags: settings.instance_flags,
+        backend_options: default(),
+    });
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: settings.power_preference,
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    }))
+    .ok_or(InitError::NoAdapter)?;
+    let adapter_info = adapter.get_info();
+    info!("{adapter_info:?}");
+
+    let mut features = wgpu::Features::empty();
+    let mut limits = settings.limits.clone();
+    if matches!(settings.priority, WgpuSettingsPriority::Functionality) {
+        features = adapter.features();
+        if adapter_info.device_type == wgpu::DeviceType::DiscreteGpu {
+            features -= wgpu::Features::MAPPABLE_PRIMARY_BUFFERS;
+        }
+        // same as bevy, these can cause DeviceLost failures on platforms that report them
+        features -= wgpu::Features::EXPERIMENTAL_RAY_QUERY;
+        features -= wgpu::Features::EXPERIMENTAL_RAY_TRACING_ACCELERATION_STRUCTURE;
+        limits = adapter.limits();
+    }
+    if let Some(disabled_features) = settings.disabled_features {
+        features -= disabled_features;
+    }
+    features |= settings.features;
+
+    let open_device = unsafe {
+        adapter.as_hal::<Vulkan, _, _>(|hal_adapter| {
+            let hal_adapter = hal_adapter.ok_or(InitError::NotVulkan)?;
+            open_device(hal_adapter, features, &settings.memory_hints)
+        })
+    }?;
+    let (device, queue) = unsafe {
+        adapter.create_device_from_hal::<Vulkan>(
+            open_device,
+            &wgpu::DeviceDescriptor {
+                label: settings.device_label.as_deref(),
+                required_features: features,
+                required_limits: limits,
+                memory_hints: settings.memory_hints.clone(),
+            },
+            settings.trace_path.as_deref(),
+        )
+    }?;
+
+    Ok(RenderResources(
+        RenderDevice::from(device),
+        RenderQueue(Arc::new(WgpuWrapper::new(queue))),
+        RenderAdapterInfo(WgpuWrapper::new(adapter_info)),
+        RenderAdapter(Arc::new(WgpuWrapper::new(adapter))),
+        RenderInstance(Arc::new(WgpuWrapper::new(instance))),
+    ))
+}
+
+/// Mirrors `wgpu::hal::Adapter::open` for the Vulkan backend, but with the dmabuf extensions
+/// added to the enabled device extensions.
+unsafe fn open_device(
+    adapter: &wgpu::hal::vulkan::Adapter,
+    features: wgpu::Features,
+    memory_hints: &wgpu::MemoryHints,
+) -> Result<OpenDevice<Vulkan>, InitError> {
+    let mut enabled_extensions = adapter.required_device_extensions(features);
+    let missing_extensions = required_device_extensions()
+        .into_iter()
+        .filter(|ext| {
+            !adapter
+                .physical_device_capabilities()
+                .supports_extension(ext)
+        })
+        .collect::<Vec<_>>();
+    if !missing_extensions.is_empty() {
+        return Err(InitError::MissingExtensions(missing_extensions));
+    }
+    for ext in required_device_extensions() {
+        if !enabled_extensions.contains(&ext) {
+            enabled_extensions.push(ext);
+        }
+    }
+    for ext in optional_device_extensions() {
+        if enabled_extensions.contains(&ext) {
+            continue;
+        }
+        if adapter
+            .physical_device_capabilities()
+            .supports_extension(ext)
+        {
+            enabled_extensions.push(ext);
+        } else {
+            warn!("optional dmabuf device extension {ext:?} is not supported");
+        }
+    }
+    let mut enabled_phd_features = adapter.physical_device_features(&enabled_extensions, features);
+
+    // wgpu-hal always uses the first queue family
+    let family_index = 0;
+    let family_info = vk::DeviceQueueCreateInfo::default()
+        .queue_family_index(family_index)
+        .queue_priorities(&[1.0]);
+    let family_infos = [family_info];
+    let str_pointers = enabled_extensions
+        .iter()
+        .map(|ext| ext.as_ptr())
+        .collect::<Vec<_>>();
+    let pre_info = vk::DeviceCreateInfo::default()
+        .queue_create_infos(&family_infos)
+        .enabled_extension_names(&str_pointers);
+    let info = enabled_phd_features.add_to_device_create(pre_info);
+    let raw_device = unsafe {
+        adapter.shared_instance().raw_instance().create_device(
+            adapter.raw_physical_device(),
+            &info,
+            None,
+        )
+    }?;
+
+    Ok(unsafe {
+        adapter.device_from_raw(
+            raw_device,
+            None,
+            &enabled_extensions,
+            features,
+            memory_hints,
+            family_index,
+            0,
+        )
+    }?)
+}
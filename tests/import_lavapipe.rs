@@ -0,0 +1,152 @@
+// Imports a LINEAR udmabuf on lavapipe, acquires it and reads it back.
+//
+// Needs read/write access to `/dev/udmabuf` and lavapipe as the Vulkan driver, e.g. with
+// `VK_DRIVER_FILES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json`, so it's ignored by default and
+// run with `cargo test --test import_lavapipe -- --ignored`.
+
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+use bevy::render::settings::WgpuSettings;
+use bevy_dmabuf::{
+    dmatex::{ColorMatrix, ColorRange, Dmatex, DmatexPlane, DmatexTransform, Resolution},
+    import::{DmatexUsage, DropCallback, import_texture},
+    wgpu_init::create_render_resources,
+};
+use drm_fourcc::{DrmFourcc, DrmModifier};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 4;
+// a multiple of wgpu's COPY_BYTES_PER_ROW_ALIGNMENT, so the readback has the same layout
+const STRIDE: u32 = WIDTH * 4;
+
+/// `struct udmabuf_create` from `linux/udmabuf.h`
+#[repr(C)]
+struct UdmabufCreate {
+    memfd: u32,
+    flags: u32,
+    offset: u64,
+    size: u64,
+}
+
+const UDMABUF_FLAGS_CLOEXEC: u32 = 0x01;
+/// `_IOW('u', 0x42, struct udmabuf_create)`
+const UDMABUF_CREATE: libc::c_ulong = 0x4018_7542;
+
+/// A dmabuf with `contents`, backed by a sealed memfd.
+fn create_udmabuf(contents: &[u8]) -> io::Result<OwnedFd> {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let size = contents.len().next_multiple_of(page_size);
+
+    let memfd = unsafe { libc::memfd_create(c"dmatex".as_ptr(), libc::MFD_ALLOW_SEALING) };
+    if memfd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut memfd = std::fs::File::from(unsafe { OwnedFd::from_raw_fd(memfd) });
+    memfd.set_len(size as u64)?;
+    memfd.write_all(contents)?;
+    // udmabuf requires the memfd to be unshrinkable
+    if unsafe { libc::fcntl(memfd.as_raw_fd(), libc::F_ADD_SEALS, libc::F_SEAL_SHRINK) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let udmabuf = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/udmabuf")?;
+    let create = UdmabufCreate {
+        memfd: memfd.as_raw_fd() as u32,
+        flags: UDMABUF_FLAGS_CLOEXEC,
+        offset: 0,
+        size: size as u64,
+    };
+    let fd = unsafe { libc::ioctl(udmabuf.as_raw_fd(), UDMABUF_CREATE, &create) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+#[test]
+#[ignore = "needs /dev/udmabuf and lavapipe"]
+fn import_linear_udmabuf() {
+    let pixels = (0..HEIGHT)
+        .flat_map(|y| (0..WIDTH).flat_map(move |x| [x as u8 * 4, y as u8 * 64, 0x80, 0xff]))
+        .collect::<Vec<_>>();
+    let dmabuf = create_udmabuf(&pixels).expect("unable to create udmabuf");
+    let resources = create_render_resources(&WgpuSettings::default())
+        .expect("unable to create dmabuf capable device");
+    assert_eq!(
+        resources.2.driver, "llvmpipe",
+        "{} is not lavapipe",
+        resources.2.name
+    );
+    let (device, queue) = (resources.0, resources.1);
+
+    let buf = Dmatex {
+        planes: vec![DmatexPlane {
+            dmabuf_fd: dmabuf.into(),
+            modifier: DrmModifier::Linear.into(),
+            offset: 0,
+            stride: STRIDE as i32,
+        }],
+        res: Resolution {
+            x: WIDTH,
+            y: HEIGHT,
+        },
+        format: DrmFourcc::Argb8888 as u32,
+        flip_y: false,
+        transform: DmatexTransform::Normal,
+        srgb: false,
+        color_matrix: ColorMatrix::default(),
+        color_range: ColorRange::default(),
+        explicit_sync: None,
+        device: None,
+    };
+    let mut imported = import_texture(
+        &device,
+        buf,
+        DropCallback(None),
+        DmatexUsage::SAMPLING | DmatexUsage::COPY_SOURCE,
+    )
+    .expect("importing the udmabuf failed");
+    assert_eq!(imported.texture().format(), wgpu::TextureFormat::Bgra8Unorm);
+    // the first acquire restores the contents after wgpu's first layout transition
+    imported
+        .acquire(&device, &queue)
+        .expect("acquiring the udmabuf failed");
+
+    let readback = device.wgpu_device().create_buffer(&wgpu::BufferDescriptor {
+        label: Some("udmabuf readback"),
+        size: pixels.len() as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device
+        .wgpu_device()
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_texture_to_buffer(
+        imported.texture().as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &readback,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(STRIDE),
+                rows_per_image: Some(HEIGHT),
+            },
+        },
+        imported.texture().size(),
+    );
+    queue.submit([encoder.finish()]);
+    imported
+        .release(&device, &queue)
+        .expect("releasing the udmabuf failed");
+    readback.slice(..).map_async(wgpu::MapMode::Read, |result| {
+        result.expect("mapping the readback buffer failed");
+    });
+    device.wgpu_device().poll(wgpu::Maintain::Wait);
+    assert!(*readback.slice(..).get_mapped_range() == pixels[..]);
+}